@@ -0,0 +1,2 @@
+DROP TABLE spt_songs_spt_playlists;
+DROP TABLE spt_playlists
\ No newline at end of file
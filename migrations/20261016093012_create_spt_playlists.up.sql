@@ -0,0 +1,9 @@
+CREATE TABLE spt_playlists (
+  id VARCHAR NOT NULL PRIMARY KEY,
+  name VARCHAR
+);
+CREATE TABLE spt_songs_spt_playlists (
+  spt_song_id VARCHAR REFERENCES spt_songs(id),
+  spt_playlist_id VARCHAR REFERENCES spt_playlists(id),
+  PRIMARY KEY(spt_song_id, spt_playlist_id)
+)
\ No newline at end of file
@@ -0,0 +1,6 @@
+ALTER TABLE spt_songs_spt_playlists
+    DROP position;
+ALTER TABLE spt_songs_spt_playlists
+    DROP added_by;
+ALTER TABLE spt_songs_spt_playlists
+    DROP added_at
\ No newline at end of file
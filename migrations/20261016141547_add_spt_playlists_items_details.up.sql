@@ -0,0 +1,6 @@
+ALTER TABLE spt_songs_spt_playlists
+  ADD added_at VARCHAR;
+ALTER TABLE spt_songs_spt_playlists
+  ADD added_by VARCHAR;
+ALTER TABLE spt_songs_spt_playlists
+  ADD position INTEGER
\ No newline at end of file
@@ -0,0 +1,4 @@
+ALTER TABLE spt_playlists
+    DROP synced_at;
+ALTER TABLE spt_playlists
+    DROP snapshot_id
\ No newline at end of file
@@ -0,0 +1,4 @@
+ALTER TABLE spt_playlists
+  ADD snapshot_id VARCHAR;
+ALTER TABLE spt_playlists
+  ADD synced_at VARCHAR
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE spt_songs_spt_playlists
+    DROP removed_at
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE spt_songs_spt_playlists
+  ADD removed_at VARCHAR
\ No newline at end of file
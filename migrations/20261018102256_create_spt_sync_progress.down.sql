@@ -0,0 +1,2 @@
+DROP TABLE spt_sync_progress_songs;
+DROP TABLE spt_sync_progress
\ No newline at end of file
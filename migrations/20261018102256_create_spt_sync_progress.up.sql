@@ -0,0 +1,10 @@
+CREATE TABLE spt_sync_progress (
+  spt_playlist_id VARCHAR NOT NULL PRIMARY KEY REFERENCES spt_playlists(id),
+  snapshot_id VARCHAR NOT NULL,
+  next_offset INTEGER NOT NULL
+);
+CREATE TABLE spt_sync_progress_songs (
+  spt_playlist_id VARCHAR NOT NULL REFERENCES spt_playlists(id),
+  spt_song_id VARCHAR NOT NULL,
+  PRIMARY KEY(spt_playlist_id, spt_song_id)
+)
\ No newline at end of file
@@ -0,0 +1,14 @@
+ALTER TABLE spt_songs
+    DROP track_number;
+ALTER TABLE spt_songs
+    DROP disc_number;
+ALTER TABLE spt_albums
+    DROP total_tracks;
+ALTER TABLE spt_albums
+    DROP upc;
+ALTER TABLE spt_albums
+    DROP label;
+ALTER TABLE spt_albums
+    DROP release_date_precision;
+ALTER TABLE spt_albums
+    DROP release_date
\ No newline at end of file
@@ -0,0 +1,14 @@
+ALTER TABLE spt_albums
+  ADD release_date VARCHAR;
+ALTER TABLE spt_albums
+  ADD release_date_precision VARCHAR;
+ALTER TABLE spt_albums
+  ADD label VARCHAR;
+ALTER TABLE spt_albums
+  ADD upc VARCHAR;
+ALTER TABLE spt_albums
+  ADD total_tracks INTEGER;
+ALTER TABLE spt_songs
+  ADD disc_number INTEGER;
+ALTER TABLE spt_songs
+  ADD track_number INTEGER
\ No newline at end of file
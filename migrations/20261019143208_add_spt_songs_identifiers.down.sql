@@ -0,0 +1,12 @@
+ALTER TABLE spt_songs
+    DROP spotify_url;
+ALTER TABLE spt_songs
+    DROP available_markets;
+ALTER TABLE spt_songs
+    DROP is_local;
+ALTER TABLE spt_songs
+    DROP popularity;
+ALTER TABLE spt_songs
+    DROP explicit;
+ALTER TABLE spt_songs
+    DROP isrc
\ No newline at end of file
@@ -0,0 +1,12 @@
+ALTER TABLE spt_songs
+  ADD isrc VARCHAR;
+ALTER TABLE spt_songs
+  ADD explicit BOOLEAN;
+ALTER TABLE spt_songs
+  ADD popularity INTEGER;
+ALTER TABLE spt_songs
+  ADD is_local BOOLEAN;
+ALTER TABLE spt_songs
+  ADD available_markets VARCHAR;
+ALTER TABLE spt_songs
+  ADD spotify_url VARCHAR
\ No newline at end of file
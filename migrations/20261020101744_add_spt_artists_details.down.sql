@@ -0,0 +1,7 @@
+DROP TABLE spt_artists_images;
+DROP TABLE spt_artists_spt_genres;
+DROP TABLE spt_genres;
+ALTER TABLE spt_artists
+    DROP followers;
+ALTER TABLE spt_artists
+    DROP popularity
\ No newline at end of file
@@ -0,0 +1,19 @@
+ALTER TABLE spt_artists
+  ADD popularity INTEGER;
+ALTER TABLE spt_artists
+  ADD followers INTEGER;
+CREATE TABLE spt_genres (
+  name VARCHAR NOT NULL PRIMARY KEY
+);
+CREATE TABLE spt_artists_spt_genres (
+  spt_artist_id VARCHAR NOT NULL REFERENCES spt_artists(id),
+  spt_genre VARCHAR NOT NULL REFERENCES spt_genres(name),
+  PRIMARY KEY(spt_artist_id, spt_genre)
+);
+CREATE TABLE spt_artists_images (
+  artist_id VARCHAR NOT NULL REFERENCES spt_artists(id),
+  url VARCHAR NOT NULL,
+  height INTEGER,
+  width INTEGER,
+  PRIMARY KEY(artist_id, url)
+)
\ No newline at end of file
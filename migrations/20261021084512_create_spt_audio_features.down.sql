@@ -0,0 +1 @@
+DROP TABLE spt_audio_features
\ No newline at end of file
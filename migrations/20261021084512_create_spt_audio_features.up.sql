@@ -0,0 +1,11 @@
+CREATE TABLE spt_audio_features (
+  spt_song_id VARCHAR NOT NULL PRIMARY KEY REFERENCES spt_songs(id),
+  tempo REAL NOT NULL,
+  key INTEGER NOT NULL,
+  mode INTEGER NOT NULL,
+  time_signature INTEGER NOT NULL,
+  energy REAL NOT NULL,
+  valence REAL NOT NULL,
+  danceability REAL NOT NULL,
+  loudness REAL NOT NULL
+)
\ No newline at end of file
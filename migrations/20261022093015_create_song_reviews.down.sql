@@ -0,0 +1 @@
+DROP TABLE song_reviews
\ No newline at end of file
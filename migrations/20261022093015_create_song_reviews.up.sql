@@ -0,0 +1,5 @@
+CREATE TABLE song_reviews (
+  song_id VARCHAR(12) NOT NULL PRIMARY KEY REFERENCES songs(id),
+  status VARCHAR NOT NULL,
+  reviewed_at VARCHAR NOT NULL
+)
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE song_reviews
+    DROP rating
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE song_reviews
+  ADD rating INTEGER CHECK (rating BETWEEN 1 AND 5)
\ No newline at end of file
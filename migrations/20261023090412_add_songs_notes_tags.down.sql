@@ -0,0 +1,4 @@
+DROP TABLE songs_tags;
+DROP TABLE tags;
+ALTER TABLE songs
+    DROP notes
\ No newline at end of file
@@ -0,0 +1,10 @@
+ALTER TABLE songs
+  ADD notes TEXT;
+CREATE TABLE tags (
+  name VARCHAR NOT NULL PRIMARY KEY
+);
+CREATE TABLE songs_tags (
+  song_id VARCHAR(12) NOT NULL REFERENCES songs(id),
+  tag VARCHAR NOT NULL REFERENCES tags(name),
+  PRIMARY KEY(song_id, tag)
+)
\ No newline at end of file
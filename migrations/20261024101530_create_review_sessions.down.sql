@@ -0,0 +1 @@
+DROP TABLE review_sessions
\ No newline at end of file
@@ -0,0 +1,6 @@
+CREATE TABLE review_sessions (
+  name VARCHAR NOT NULL PRIMARY KEY,
+  seed INTEGER NOT NULL,
+  current_spt_song_id VARCHAR,
+  updated_at VARCHAR NOT NULL
+)
\ No newline at end of file
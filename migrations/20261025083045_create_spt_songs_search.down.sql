@@ -0,0 +1 @@
+DROP TABLE spt_songs_search
\ No newline at end of file
@@ -0,0 +1,18 @@
+CREATE VIRTUAL TABLE spt_songs_search USING fts5(
+  spt_song_id UNINDEXED,
+  title,
+  artist,
+  album,
+  tags,
+  tokenize = 'unicode61 remove_diacritics 2'
+);
+INSERT INTO spt_songs_search(spt_song_id, title, artist, album, tags)
+SELECT spt_songs.id, spt_songs.title,
+  coalesce((SELECT group_concat(spt_artists.name, ' ')
+    FROM spt_songs_spt_artists
+    INNER JOIN spt_artists ON spt_artists.id = spt_songs_spt_artists.spt_artist_id
+    WHERE spt_songs_spt_artists.spt_song_id = spt_songs.id), spt_songs.artist),
+  spt_albums.name,
+  (SELECT group_concat(tag, ' ') FROM songs_tags WHERE song_id = spt_songs.song)
+FROM spt_songs
+INNER JOIN spt_albums ON spt_albums.id = spt_songs.album
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE spt_covers_data
\ No newline at end of file
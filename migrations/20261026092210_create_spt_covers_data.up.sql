@@ -0,0 +1,5 @@
+CREATE TABLE spt_covers_data (
+  url VARCHAR NOT NULL PRIMARY KEY,
+  data BLOB NOT NULL,
+  fetched_at VARCHAR NOT NULL
+)
\ No newline at end of file
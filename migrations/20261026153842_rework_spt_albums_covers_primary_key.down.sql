@@ -0,0 +1,12 @@
+CREATE TABLE spt_albums_covers_largest (
+  album_id VARCHAR NOT NULL PRIMARY KEY REFERENCES spt_albums(id),
+  url VARCHAR NOT NULL,
+  height INTEGER NOT NULL,
+  width INTEGER NOT NULL
+);
+INSERT OR IGNORE INTO spt_albums_covers_largest(album_id, url, height, width)
+SELECT album_id, url, coalesce(height, 0), coalesce(width, 0) FROM spt_albums_covers
+ORDER BY album_id, coalesce(width, 0) DESC;
+DROP TABLE spt_albums_covers;
+ALTER TABLE spt_albums_covers_largest
+    RENAME TO spt_albums_covers
\ No newline at end of file
@@ -0,0 +1,12 @@
+CREATE TABLE spt_albums_covers_sizes (
+  album_id VARCHAR NOT NULL REFERENCES spt_albums(id),
+  url VARCHAR NOT NULL,
+  height INTEGER,
+  width INTEGER,
+  PRIMARY KEY(album_id, url)
+);
+INSERT INTO spt_albums_covers_sizes(album_id, url, height, width)
+SELECT album_id, url, height, width FROM spt_albums_covers;
+DROP TABLE spt_albums_covers;
+ALTER TABLE spt_albums_covers_sizes
+  RENAME TO spt_albums_covers
\ No newline at end of file
@@ -0,0 +1,145 @@
+use std::{collections::HashSet, env};
+
+use anyhow::{anyhow, Context};
+use rspotify::{prelude::*, scopes, AuthCodePkceSpotify, Config, Credentials, OAuth};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+use crate::retry::RetryPolicy;
+
+/// Used when `RSPOTIFY_REDIRECT_URI` is not set, it must be allowed in the
+/// Spotify app settings.
+pub const DEFAULT_REDIRECT_URI: &str = "http://127.0.0.1:8888/callback";
+
+/// What `sync` needs: private and collaborative playlists, Liked Songs, saved
+/// albums and followed artists.
+fn scopes() -> HashSet<String> {
+    scopes!(
+        "playlist-read-private",
+        "playlist-read-collaborative",
+        "user-library-read",
+        "user-follow-read"
+    )
+}
+
+/// A PKCE client only needs the client ID. Its token is cached at
+/// `config.cache_path` and refreshed when it expires.
+fn pkce_client(config: Config) -> anyhow::Result<AuthCodePkceSpotify> {
+    let creds = Credentials::from_env().ok_or_else(|| anyhow!("RSPOTIFY_CLIENT_ID must be set"))?;
+    let oauth = OAuth {
+        redirect_uri: env::var("RSPOTIFY_REDIRECT_URI").unwrap_or_else(|_| DEFAULT_REDIRECT_URI.to_owned()),
+        scopes: scopes(),
+        ..Default::default()
+    };
+    let config = Config {
+        token_cached: true,
+        token_refreshing: true,
+        ..config
+    };
+    Ok(AuthCodePkceSpotify::with_config(creds, oauth, config))
+}
+
+/// Runs the authorization code flow with PKCE: opens the authorization page
+/// and waits for Spotify to redirect the browser to the local listener.
+pub async fn login(config: Config) -> anyhow::Result<()> {
+    let mut spotify = pkce_client(config)?;
+    let address = listen_address(&spotify.oauth.redirect_uri)?;
+    let listener = TcpListener::bind(&address)
+        .await
+        .with_context(|| format!("cannot listen on {address} for the login redirect"))?;
+
+    let url = spotify.get_authorize_url(None)?;
+    println!("Log in to Spotify in your browser, or open this URL:\n\n    {url}\n");
+    if let Err(error) = open::that(&url) {
+        eprintln!("Could not open the browser: {error}");
+    }
+
+    let code = wait_for_code(&spotify, &listener, &address).await?;
+    spotify.request_token(&code).await?;
+    let user = spotify.current_user().await?;
+    println!(
+        "Logged in as {}, token saved to {}",
+        user.display_name.as_deref().unwrap_or(user.id.id()),
+        spotify.config.cache_path.display()
+    );
+    Ok(())
+}
+
+/// The client of the logged in user, `None` if `exospot login` was never run.
+pub async fn user_client(config: Config, retry: &RetryPolicy) -> anyhow::Result<Option<AuthCodePkceSpotify>> {
+    if !config.cache_path.exists() {
+        return Ok(None);
+    }
+    let spotify = pkce_client(config)?;
+    let token = spotify
+        .read_token_cache(true)
+        .await?
+        .ok_or_else(|| anyhow!("the saved Spotify login misses some permissions, run `exospot login` again"))?;
+    let expired = token.is_expired();
+    *spotify.token.lock().await.unwrap() = Some(token);
+    if expired {
+        retry
+            .run(|| spotify.refresh_token())
+            .await
+            .context("cannot refresh the Spotify login, run `exospot login` again")?;
+    }
+    Ok(Some(spotify))
+}
+
+/// `host:port` of an `http://` redirect URI.
+fn listen_address(redirect_uri: &str) -> anyhow::Result<String> {
+    let authority = redirect_uri
+        .strip_prefix("http://")
+        .and_then(|rest| rest.split('/').next())
+        .ok_or_else(|| anyhow!("redirect URI {redirect_uri} must be a local http:// URL"))?;
+    Ok(if authority.contains(':') {
+        authority.to_owned()
+    } else {
+        format!("{authority}:80")
+    })
+}
+
+/// Answers every request to the listener until one carries the authorization
+/// code, browsers also ask for things like `/favicon.ico`.
+async fn wait_for_code(spotify: &AuthCodePkceSpotify, listener: &TcpListener, address: &str) -> anyhow::Result<String> {
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+        while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+            let read = stream.read(&mut buf).await?;
+            if read == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..read]);
+        }
+        let request = String::from_utf8_lossy(&request);
+        let path = request
+            .lines()
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .unwrap_or("/");
+
+        let code = spotify.parse_response_code(&format!("http://{address}{path}"));
+        let denied = path.contains("error=");
+        let (status, body) = match (&code, denied) {
+            (Some(_), _) => ("200 OK", "Logged in to Spotify, you can close this tab."),
+            (None, true) => ("200 OK", "Spotify login was denied, you can close this tab."),
+            (None, false) => ("404 Not Found", "Not found"),
+        };
+        let response = format!(
+            "HTTP/1.1 {status}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await?;
+
+        match code {
+            Some(code) => return Ok(code),
+            None if denied => return Err(anyhow!("Spotify login was denied")),
+            None => continue,
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use bytes::Bytes;
+use futures::StreamExt;
+use sqlx::SqlitePool;
+
+/// The image at `url` from the cover cache, `None` if it was never downloaded.
+pub async fn cached(conn: &SqlitePool, url: &str) -> sqlx::Result<Option<Bytes>> {
+    let data = sqlx::query_scalar!("SELECT data FROM spt_covers_data WHERE url = $1", url)
+        .fetch_optional(conn)
+        .await?;
+    Ok(data.map(Bytes::from))
+}
+
+/// The image at `url`, downloaded and cached on first use.
+pub async fn get(conn: &SqlitePool, client: &reqwest::Client, url: &str) -> anyhow::Result<Bytes> {
+    if let Some(data) = cached(conn, url).await? {
+        return Ok(data);
+    }
+    download(conn, client, url).await
+}
+
+async fn download(conn: &SqlitePool, client: &reqwest::Client, url: &str) -> anyhow::Result<Bytes> {
+    let data = client.get(url).send().await?.error_for_status()?.bytes().await?;
+    let bytes = data.as_ref();
+    let fetched_at = chrono::Utc::now().to_rfc3339();
+    sqlx::query!(
+        "INSERT OR REPLACE INTO spt_covers_data(url, data, fetched_at) VALUES ($1, $2, $3)",
+        url,
+        bytes,
+        fetched_at
+    )
+    .execute(conn)
+    .await?;
+    Ok(data)
+}
+
+/// Downloads every cover size that is not cached yet, so the review works
+/// offline. Failed downloads are reported and retried on the next call.
+pub async fn cache_missing(conn: &SqlitePool, concurrency: usize) -> anyhow::Result<()> {
+    let urls = sqlx::query_scalar!(
+        "SELECT DISTINCT url FROM spt_albums_covers WHERE url NOT IN (SELECT url FROM spt_covers_data)"
+    )
+    .fetch_all(conn)
+    .await?;
+    let client = reqwest::Client::new();
+    let results: Vec<_> = futures::stream::iter(&urls)
+        .map(|url| download(conn, &client, url))
+        .buffer_unordered(concurrency)
+        .collect()
+        .await;
+    let failed: Vec<_> = results.into_iter().filter_map(Result::err).collect();
+    for error in &failed {
+        eprintln!("Could not download a cover: {error:#}");
+    }
+    println!("Cached {} of {} missing covers", urls.len() - failed.len(), urls.len());
+    Ok(())
+}
+
+/// A row of `spt_albums_covers`, sizes are in pixels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cover {
+    pub url: String,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+}
+
+/// Every size of the cover of an album, `spt_albums.id`.
+pub async fn sizes(conn: &SqlitePool, album_id: &str) -> sqlx::Result<Vec<Cover>> {
+    sqlx::query_as!(
+        Cover,
+        "SELECT url, width, height FROM spt_albums_covers WHERE album_id = $1",
+        album_id
+    )
+    .fetch_all(conn)
+    .await
+}
+
+/// The smallest cover at least `min_width` pixels wide, the largest one when
+/// none is that big. A cover without a size counts as the smallest.
+pub fn pick(covers: &[Cover], min_width: u32) -> Option<&Cover> {
+    let width = |cover: &&Cover| cover.width.unwrap_or_default();
+    covers
+        .iter()
+        .filter(|cover| width(cover) >= i64::from(min_width))
+        .min_by_key(width)
+        .or_else(|| covers.iter().max_by_key(width))
+}
@@ -0,0 +1,39 @@
+use std::{collections::HashSet, path::Path};
+
+use sqlx::{sqlite::SqliteConnectOptions, SqlitePool};
+
+pub async fn connect(path: &Path) -> anyhow::Result<SqlitePool> {
+    let options = SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(true);
+    Ok(SqlitePool::connect_with(options).await?)
+}
+
+pub async fn migrate(conn: &SqlitePool) -> anyhow::Result<()> {
+    sqlx::migrate!().run(conn).await?;
+    Ok(())
+}
+
+/// Descriptions of the migrations `migrate` would apply, oldest first.
+pub async fn pending(conn: &SqlitePool) -> anyhow::Result<Vec<String>> {
+    // Created by the first migration
+    let migrated: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations')",
+    )
+    .fetch_one(conn)
+    .await?;
+    let applied: HashSet<i64> = if migrated {
+        sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success")
+            .fetch_all(conn)
+            .await?
+            .into_iter()
+            .collect()
+    } else {
+        HashSet::new()
+    };
+    Ok(sqlx::migrate!()
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration() && !applied.contains(&migration.version))
+        .map(|migration| migration.description.to_string())
+        .collect())
+}
@@ -0,0 +1,130 @@
+use anyhow::anyhow;
+use sqlx::SqlitePool;
+
+/// Where a song stands in the review. Songs without a `song_reviews` row are
+/// pending.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ReviewStatus {
+    #[default]
+    Pending,
+    /// Postponed, comes back after the pending songs
+    Later,
+    Kept,
+    Rejected,
+}
+
+impl ReviewStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReviewStatus::Pending => "pending",
+            ReviewStatus::Later => "later",
+            ReviewStatus::Kept => "kept",
+            ReviewStatus::Rejected => "rejected",
+        }
+    }
+
+    /// Rank in the review queue: unreviewed songs first, then the postponed
+    /// ones, then those already decided.
+    pub fn queue_rank(self) -> u8 {
+        match self {
+            ReviewStatus::Pending => 0,
+            ReviewStatus::Later => 1,
+            ReviewStatus::Kept | ReviewStatus::Rejected => 2,
+        }
+    }
+}
+
+impl std::str::FromStr for ReviewStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "pending" => Ok(ReviewStatus::Pending),
+            "later" => Ok(ReviewStatus::Later),
+            "kept" => Ok(ReviewStatus::Kept),
+            "rejected" => Ok(ReviewStatus::Rejected),
+            _ => Err(anyhow!("unknown review status {s}")),
+        }
+    }
+}
+
+/// A `song_reviews` row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Review {
+    pub status: ReviewStatus,
+    /// From 1 to 5 stars
+    pub rating: Option<i64>,
+    pub reviewed_at: String,
+}
+
+pub async fn get(conn: &SqlitePool, song_id: &str) -> anyhow::Result<Option<Review>> {
+    let review = sqlx::query!("SELECT status, rating, reviewed_at FROM song_reviews WHERE song_id = $1", song_id)
+        .fetch_optional(conn)
+        .await?;
+    review
+        .map(|review| {
+            Ok(Review {
+                status: review.status.parse()?,
+                rating: review.rating,
+                reviewed_at: review.reviewed_at,
+            })
+        })
+        .transpose()
+}
+
+/// Records the decision taken on a song, `songs.id`, replacing the previous one.
+pub async fn set_status(conn: &SqlitePool, song_id: &str, status: ReviewStatus) -> sqlx::Result<()> {
+    let status = status.as_str();
+    let reviewed_at = chrono::Utc::now().to_rfc3339();
+    sqlx::query!(
+        "INSERT INTO song_reviews(song_id, status, reviewed_at) VALUES ($1, $2, $3)
+        ON CONFLICT(song_id) DO UPDATE SET status = excluded.status, reviewed_at = excluded.reviewed_at",
+        song_id,
+        status,
+        reviewed_at
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Rates a song from 1 to 5 stars, without deciding on it.
+pub async fn set_rating(conn: &SqlitePool, song_id: &str, rating: u8) -> sqlx::Result<()> {
+    let pending = ReviewStatus::Pending.as_str();
+    let reviewed_at = chrono::Utc::now().to_rfc3339();
+    sqlx::query!(
+        "INSERT INTO song_reviews(song_id, status, rating, reviewed_at) VALUES ($1, $2, $3, $4)
+        ON CONFLICT(song_id) DO UPDATE SET rating = excluded.rating, reviewed_at = excluded.reviewed_at",
+        song_id,
+        pending,
+        rating,
+        reviewed_at
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Puts back the review a song had before a decision, `None` if it had none.
+pub async fn restore(conn: &SqlitePool, song_id: &str, review: Option<&Review>) -> sqlx::Result<()> {
+    match review {
+        Some(review) => {
+            let status = review.status.as_str();
+            sqlx::query!(
+                "INSERT OR REPLACE INTO song_reviews(song_id, status, rating, reviewed_at) VALUES ($1, $2, $3, $4)",
+                song_id,
+                status,
+                review.rating,
+                review.reviewed_at
+            )
+            .execute(conn)
+            .await?;
+        }
+        None => {
+            sqlx::query!("DELETE FROM song_reviews WHERE song_id = $1", song_id)
+                .execute(conn)
+                .await?;
+        }
+    }
+    Ok(())
+}
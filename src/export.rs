@@ -0,0 +1,121 @@
+use std::{fs::File, io, path::Path};
+
+use clap::ValueEnum;
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use crate::decisions::ReviewStatus;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Serialize)]
+struct ExportedSong {
+    id: String,
+    title: String,
+    artist: String,
+    artists: String,
+    album: String,
+    album_kind: String,
+    release_date: Option<String>,
+    label: Option<String>,
+    upc: Option<String>,
+    disc_number: Option<i64>,
+    track_number: Option<i64>,
+    total_tracks: Option<i64>,
+    isrc: Option<String>,
+    explicit: Option<bool>,
+    popularity: Option<i64>,
+    playlists: String,
+    review_status: String,
+    rating: Option<i64>,
+    reviewed_at: Option<String>,
+    notes: Option<String>,
+    tags: String,
+    duration_ms: i64,
+    preview_url: Option<String>,
+    spotify_url: String,
+}
+
+pub async fn export(conn: &SqlitePool, format: Format, output: Option<&Path>) -> anyhow::Result<()> {
+    let songs = sqlx::query!(
+        "SELECT spt_songs.id, spt_songs.title, spt_songs.artist, spt_songs.duration, spt_songs.preview_url,
+            spt_songs.disc_number, spt_songs.track_number, spt_songs.isrc, spt_songs.explicit, spt_songs.popularity,
+            spt_songs.spotify_url, song_reviews.status AS \"review_status?\", song_reviews.rating AS \"rating?\",
+            song_reviews.reviewed_at AS \"reviewed_at?\",
+            spt_albums.name AS album_name, spt_albums.kind AS album_kind, spt_albums.release_date,
+            spt_albums.label, spt_albums.upc, spt_albums.total_tracks,
+            (SELECT group_concat(spt_artists.name, ', ')
+                FROM spt_songs_spt_artists
+                INNER JOIN spt_artists ON spt_songs_spt_artists.spt_artist_id = spt_artists.id
+                WHERE spt_songs_spt_artists.spt_song_id = spt_songs.id) AS \"artists: String\",
+            (SELECT group_concat(coalesce(spt_playlists.name, spt_playlists.id), ', ')
+                FROM spt_songs_spt_playlists
+                INNER JOIN spt_playlists ON spt_songs_spt_playlists.spt_playlist_id = spt_playlists.id
+                WHERE spt_songs_spt_playlists.spt_song_id = spt_songs.id
+                    AND spt_songs_spt_playlists.removed_at IS NULL) AS \"playlists: String\",
+            songs.notes,
+            (SELECT group_concat(tag, ', ') FROM (SELECT tag FROM songs_tags WHERE song_id = spt_songs.song ORDER BY tag))
+                AS \"tags: String\"
+        FROM spt_songs
+        INNER JOIN spt_albums ON spt_songs.album = spt_albums.id
+        INNER JOIN songs ON songs.id = spt_songs.song
+        LEFT JOIN song_reviews ON song_reviews.song_id = spt_songs.song
+        ORDER BY spt_songs.artist, spt_songs.title"
+    )
+    .fetch_all(conn)
+    .await?;
+
+    let songs: Vec<_> = songs
+        .into_iter()
+        .map(|song| ExportedSong {
+            spotify_url: song
+                .spotify_url
+                .unwrap_or_else(|| format!("https://open.spotify.com/track/{}", song.id)),
+            id: song.id,
+            title: song.title,
+            artist: song.artist,
+            artists: song.artists.unwrap_or_default(),
+            album: song.album_name,
+            album_kind: song.album_kind,
+            release_date: song.release_date,
+            label: song.label,
+            upc: song.upc,
+            disc_number: song.disc_number,
+            track_number: song.track_number,
+            total_tracks: song.total_tracks,
+            isrc: song.isrc,
+            explicit: song.explicit,
+            popularity: song.popularity,
+            playlists: song.playlists.unwrap_or_default(),
+            review_status: song
+                .review_status
+                .unwrap_or_else(|| ReviewStatus::Pending.as_str().to_owned()),
+            rating: song.rating,
+            reviewed_at: song.reviewed_at,
+            notes: song.notes,
+            tags: song.tags.unwrap_or_default(),
+            duration_ms: song.duration,
+            preview_url: song.preview_url,
+        })
+        .collect();
+
+    let writer: Box<dyn io::Write> = match output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout().lock()),
+    };
+    match format {
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(writer);
+            for song in &songs {
+                writer.serialize(song)?;
+            }
+            writer.flush()?;
+        }
+        Format::Json => serde_json::to_writer_pretty(writer, &songs)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use std::str::FromStr;
+
+use anyhow::anyhow;
+
+const PITCH_CLASSES: [&str; 12] = ["C", "C#", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B"];
+
+/// A row of `spt_audio_features`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AudioFeatures {
+    pub tempo: f64,
+    /// Pitch class, -1 when no key was detected
+    pub key: i64,
+    /// 1 for major, 0 for minor
+    pub mode: i64,
+    pub time_signature: i64,
+    pub energy: f64,
+    pub valence: f64,
+    pub danceability: f64,
+    /// Average loudness in dB
+    pub loudness: f64,
+}
+
+impl AudioFeatures {
+    /// Key in standard notation, like `A minor`.
+    pub fn key_name(&self) -> Option<String> {
+        let pitch = PITCH_CLASSES.get(usize::try_from(self.key).ok()?)?;
+        let mode = if self.mode == 1 { "major" } else { "minor" };
+        Some(format!("{pitch} {mode}"))
+    }
+
+    /// Key on the Camelot wheel DJs mix with, like `8A` for A minor.
+    pub fn camelot(&self) -> Option<Camelot> {
+        if !(0..12).contains(&self.key) {
+            return None;
+        }
+        let major = self.mode == 1;
+        // Minor keys share the number of their relative major, three semitones up
+        let root = if major { self.key } else { (self.key + 3) % 12 };
+        Some(Camelot {
+            number: ((root * 7 + 7) % 12 + 1) as u8,
+            major,
+        })
+    }
+}
+
+/// Position on the Camelot wheel, `1A` to `12B`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Camelot {
+    pub number: u8,
+    pub major: bool,
+}
+
+impl std::fmt::Display for Camelot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.number, if self.major { 'B' } else { 'A' })
+    }
+}
+
+impl FromStr for Camelot {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let s = s.trim();
+        let invalid = || anyhow!("{s} is not a Camelot key like 8A or 11B");
+        let (number, letter) = s.split_at(s.len().checked_sub(1).ok_or_else(invalid)?);
+        let major = match letter {
+            "A" | "a" => false,
+            "B" | "b" => true,
+            _ => return Err(invalid()),
+        };
+        match number.parse() {
+            Ok(number @ 1..=12) => Ok(Camelot { number, major }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Inclusive tempo range in BPM, written `120-128`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TempoRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl FromStr for TempoRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let (min, max) = s
+            .split_once('-')
+            .ok_or_else(|| anyhow!("{s} is not a tempo range like 120-128"))?;
+        let range = TempoRange {
+            min: min.trim().parse()?,
+            max: max.trim().parse()?,
+        };
+        if range.min > range.max {
+            return Err(anyhow!("{s} starts above its end, write it like 120-128"));
+        }
+        Ok(range)
+    }
+}
+
+/// Review queue filters on the audio features, songs without features never
+/// match an active filter.
+#[derive(Debug, Clone, Default, clap::Args)]
+pub struct FeatureFilter {
+    /// Only the songs in this tempo range, like `120-128`
+    #[arg(long)]
+    pub tempo: Option<TempoRange>,
+    /// Only the songs in this Camelot key, like `8A`
+    #[arg(long)]
+    pub key: Option<Camelot>,
+    /// Only the songs with at least this energy, from 0 to 1
+    #[arg(long)]
+    pub min_energy: Option<f64>,
+    /// Only the songs with at least this danceability, from 0 to 1
+    #[arg(long)]
+    pub min_danceability: Option<f64>,
+    /// Only the songs with at least this valence, from 0 to 1
+    #[arg(long)]
+    pub min_valence: Option<f64>,
+}
+
+impl FeatureFilter {
+    fn is_active(&self) -> bool {
+        self.tempo.is_some()
+            || self.key.is_some()
+            || self.min_energy.is_some()
+            || self.min_danceability.is_some()
+            || self.min_valence.is_some()
+    }
+
+    pub fn matches(&self, features: Option<&AudioFeatures>) -> bool {
+        let Some(features) = features else {
+            return !self.is_active();
+        };
+        self.tempo
+            .is_none_or(|range| (range.min..=range.max).contains(&features.tempo))
+            && self.key.is_none_or(|key| features.camelot() == Some(key))
+            && self.min_energy.is_none_or(|min| features.energy >= min)
+            && self.min_danceability.is_none_or(|min| features.danceability >= min)
+            && self.min_valence.is_none_or(|min| features.valence >= min)
+    }
+}
@@ -0,0 +1,142 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use reqwest::{
+    blocking::{Client, Response},
+    header::{ACCEPT_RANGES, RANGE},
+    StatusCode,
+};
+use symphonia::core::io::MediaSource;
+
+const CHUNK_SIZE: usize = 16 * 1024;
+
+/// A file read over HTTP while it downloads, for symphonia. The bytes read are
+/// kept so seeking back is free, a seek past them starts a Range request there.
+///
+/// Reads block, use it outside of async code.
+pub struct HttpSource {
+    client: Client,
+    url: String,
+    /// From Content-Length
+    len: Option<u64>,
+    /// The server answers Range requests
+    ranges: bool,
+    /// Downloaded bytes, from `start`
+    buffer: Vec<u8>,
+    start: u64,
+    /// Download of the bytes after the buffer, `None` once finished
+    response: Option<Response>,
+    position: u64,
+}
+
+impl HttpSource {
+    /// Starts downloading `url`.
+    pub fn new(client: Client, url: &str) -> anyhow::Result<Self> {
+        let response = client.get(url).send()?.error_for_status()?;
+        let ranges = response
+            .headers()
+            .get(ACCEPT_RANGES)
+            .is_some_and(|value| value.as_bytes() == b"bytes");
+        Ok(HttpSource {
+            client,
+            url: url.to_owned(),
+            len: response.content_length(),
+            ranges,
+            buffer: Vec::new(),
+            start: 0,
+            response: Some(response),
+            position: 0,
+        })
+    }
+
+    fn end(&self) -> u64 {
+        self.start + self.buffer.len() as u64
+    }
+
+    /// Downloads the next chunk into the buffer, `false` at the end of the file.
+    fn fill(&mut self) -> io::Result<bool> {
+        let Some(response) = &mut self.response else {
+            return Ok(false);
+        };
+        let mut chunk = [0; CHUNK_SIZE];
+        let read = response.read(&mut chunk)?;
+        if read == 0 {
+            self.response = None;
+        }
+        self.buffer.extend_from_slice(&chunk[..read]);
+        Ok(read > 0)
+    }
+
+    /// Drops the buffer and downloads from `offset` instead, the buffer is
+    /// kept when the request fails.
+    fn restart_at(&mut self, offset: u64) -> io::Result<()> {
+        self.response = if self.len.is_some_and(|len| offset >= len) {
+            None
+        } else {
+            let response = self
+                .client
+                .get(&self.url)
+                .header(RANGE, format!("bytes={offset}-"))
+                .send()
+                .and_then(Response::error_for_status)
+                .map_err(io::Error::other)?;
+            if response.status() != StatusCode::PARTIAL_CONTENT {
+                return Err(io::Error::other(format!("{} ignored the Range request", self.url)));
+            }
+            Some(response)
+        };
+        self.buffer.clear();
+        self.start = offset;
+        Ok(())
+    }
+}
+
+impl Read for HttpSource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.end() && !self.fill()? {
+            return Ok(0);
+        }
+        let available = &self.buffer[(self.position - self.start) as usize..];
+        let read = available.len().min(buf.len());
+        buf[..read].copy_from_slice(&available[..read]);
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for HttpSource {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+            SeekFrom::End(offset) => {
+                let len = self.len.ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "unknown length"))?;
+                len.checked_add_signed(offset)
+            }
+        };
+        let target = target.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek before the start"))?;
+
+        if target < self.start || target > self.end() {
+            if self.ranges {
+                self.restart_at(target)?;
+            } else if target > self.end() {
+                // Download up to it, the buffer starts at 0 without ranges
+                while self.end() < target && self.fill()? {}
+            } else {
+                return Err(io::Error::new(io::ErrorKind::Unsupported, "the server does not answer Range requests"));
+            }
+        }
+        // Past the end of a download without ranges, stop at its end
+        self.position = target.min(self.end());
+        Ok(self.position)
+    }
+}
+
+impl MediaSource for HttpSource {
+    fn is_seekable(&self) -> bool {
+        self.ranges && self.len.is_some()
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        self.len
+    }
+}
@@ -0,0 +1,17 @@
+pub mod auth;
+pub mod covers;
+pub mod db;
+pub mod decisions;
+pub mod export;
+pub mod features;
+pub mod http_source;
+pub mod notes;
+pub mod playlists;
+pub mod retry;
+pub mod review;
+pub mod search;
+pub mod sessions;
+pub mod stats;
+pub mod symphonia_decoder;
+pub mod sync;
+mod widgets;
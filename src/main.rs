@@ -1,514 +1,239 @@
-use anyhow::anyhow;
-use base64::Engine;
-use bytes::Bytes;
-use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, EventStream, KeyCode},
-    execute,
-    terminal::{
-        self, disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
-    },
-};
-use futures::stream::TryStreamExt;
-use futures_util::{FutureExt, StreamExt, AsyncReadExt};
-use ratatui::{
-    backend::CrosstermBackend,
-    layout::{Alignment, Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
-    text::{Span, Spans, Line},
-    widgets::{Block, Borders, Paragraph, ListItem, List, ListState},
-    Terminal, prelude::Rect,
-};
-use rodio::{Source, Sink};
-use rspotify::{
-    model::{AlbumId, PlayableItem, PlaylistId},
-    prelude::*,
-    ClientCredsSpotify, Credentials,
-};
-use sqlx::SqliteConnection;
-use sqlx::{sqlite::SqliteConnectOptions, Connection, SqlitePool};
-use symphonia::core::io::MediaSource;
-use std::{
-    error::Error,
-    io::{self, Stdout, Read},
-    process::exit,
-    sync::Arc,
-    time::Duration, vec,
-};
-use tokio::{select, sync::Mutex};
-
-use viuer::{print, Config};
-
-mod symphonia_decoder;
-mod widgets;
-
-fn setup_terminal() -> Result<Terminal<CrosstermBackend<Stdout>>, Box<dyn Error>> {
-    let mut stdout = io::stdout();
-    enable_raw_mode()?;
-    execute!(stdout, EnterAlternateScreen)?;
-    Ok(Terminal::new(CrosstermBackend::new(stdout))?)
-}
-
-fn restore_terminal(
-    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
-) -> Result<(), Box<dyn Error>> {
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen,)?;
-    Ok(terminal.show_cursor()?)
-}
-
-#[derive(Debug, Clone)]
-enum App {
-    Welcome,
-    Spotify((SpotifyUi, Vec<String>, StatefulList<(String, Color)>,)),
-}
-
-impl std::fmt::Debug for StatefulList<(std::string::String, Color)> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("StatefulList").field("state", &self.state).field("items", &self.items).finish()
-    }
-}
+use std::path::PathBuf;
 
-struct States {
-    spt_list: StatefulList<(String, Color)>,
-}
+use clap::{Parser, Subcommand};
+use exospot::{auth, db, export, features, playlists, retry, review, stats, sync};
 
-#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
-pub struct SpotifyUi {
-    title: String,
-    artist: String,
-    cover_img: Bytes,
-    album_name: String,
-    album_kind: String,
-    duration: Duration,
-}
+#[derive(Debug, Parser)]
+#[command(version, about = "Review Spotify playlists from the terminal")]
+struct Cli {
+    /// Path of the SQLite database
+    #[arg(long, global = true, env = "EXOSPOT_DB", default_value = "songs.db")]
+    db: PathBuf,
 
-fn draw(
-    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
-    app: &App,
-    mut states: &mut States,
-) -> Result<(), Box<dyn Error>> {
-    terminal.draw(|frame| {
-        match app {
-            App::Welcome => {
-                let greeting = Paragraph::new("Welcome to Exospot");
-                frame.render_widget(greeting, frame.size());
-            }
-            App::Spotify((spt_ui, songs, _)) => {
-                let spt_widget = widgets::spotify::Clear(spt_ui.clone());
-                let chunks = Layout::default()
-                    .direction(Direction::Horizontal)
-                    .constraints(
-                        [
-                            Constraint::Percentage(20),
-                            Constraint::Percentage(80),
-                        ].as_ref()
-                    )
-                    .split(frame.size());
-                frame.render_widget(spt_widget, chunks[1]);
-                
-                let items: Vec<_> = states.spt_list.items.iter().map(|song| {
-                    ListItem::new(Line::from(Span::raw(&song.0))).style(Style::default().fg(song.1))
-                }).collect();
-                let list = List::new(items)
-                    .block(Block::default().title("List").borders(Borders::ALL))
-                    // .style(Style::default().fg(Color::White))
-                    .highlight_style(
-                        Style::default()
-                            .bg(Color::LightGreen)
-                            .fg(Color::DarkGray)
-                            .add_modifier(Modifier::BOLD),
-                    )
-                    .highlight_symbol(">>");
-                // frame.render_widget(list, Rect::new(0, 0, 30, frame.size().height));
-                frame.render_stateful_widget(list, chunks[0], &mut states.spt_list.state);
-            }
-        }
-    })?;
-    Ok(())
-}
+    /// Where the Spotify login of `exospot login` is saved
+    #[arg(long, global = true, env = "EXOSPOT_TOKEN_CACHE", default_value = ".spotify_token_cache.json")]
+    token_cache: PathBuf,
 
-trait DisplayTimestamp {
-    fn display_timestamp(&self) -> Result<String, anyhow::Error>;
-}
-
-impl DisplayTimestamp for chrono::Duration {
-    fn display_timestamp(&self) -> Result<String, anyhow::Error> {
-        let mut a = chrono::Duration::from(*self);
-        let minutes = a.num_minutes();
-        a = a - chrono::Duration::from_std(std::time::Duration::from_secs((a.num_minutes()*60) as u64))?;
-        let seconds = a.num_seconds();
-        Ok(format!("{minutes:0>2}:{seconds:0>2}"))
-    }
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-async fn input(
-    tx: tokio::sync::mpsc::Sender<Event>,
-    update_tx: tokio::sync::watch::Sender<bool>,
-    mut states: Arc<Mutex<States>>,
-) {
-    let mut reader = EventStream::new();
-    loop {
-        let event = reader.next().await;
-        let Some(event) = event else { continue };
-        let Ok(event) = event else { continue };
-        match event {
-            Event::FocusGained => {}
-            Event::FocusLost => {}
-            Event::Key(key) => match key.code {
-                KeyCode::Char('q') => break,
-                _ => {}
-            },
-            Event::Mouse(_) => {}
-            Event::Paste(_) => {}
-            Event::Resize(_, _) => {
-                update_tx.send(true).unwrap();
-            }
-        }
-        tx.send(event).await.unwrap();
-    }
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Log in to Spotify to sync private playlists and the user library
+    Login {
+        #[arg(long, env = "EXOSPOT_SPOTIFY_AUTH_URL", default_value = rspotify::DEFAULT_AUTH_BASE_URL, hide = true)]
+        auth_url: String,
+    },
+    /// Pull playlist and library tracks from the Spotify Web API into the database
+    Sync {
+        /// Walk every playlist even if its snapshot ID did not change
+        #[arg(long)]
+        full: bool,
+        /// Delete the songs removed from every playlist, with their albums and artists
+        #[arg(long)]
+        prune: bool,
+        /// How many tracks are written to the database at the same time
+        #[arg(long, default_value_t = 10, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+        concurrency: usize,
+        /// Also import tempo, key, energy, danceability and the other audio features
+        #[arg(long)]
+        audio_features: bool,
+        /// Also download the album covers, so the review works offline
+        #[arg(long)]
+        covers: bool,
+        /// How many times a rate limited or failed Spotify request is retried
+        #[arg(long, default_value_t = 5)]
+        max_retries: u32,
+        #[arg(long, env = "EXOSPOT_SPOTIFY_API_URL", default_value = rspotify::DEFAULT_API_BASE_URL, hide = true)]
+        api_url: String,
+        #[arg(long, env = "EXOSPOT_SPOTIFY_AUTH_URL", default_value = rspotify::DEFAULT_AUTH_BASE_URL, hide = true)]
+        auth_url: String,
+    },
+    /// Open the review TUI (default when no subcommand is given)
+    Review {
+        /// Only review this playlist or library source, in its order
+        #[arg(long)]
+        playlist: Option<String>,
+        /// Only review the songs with an artist whose genre contains this, like `house`
+        #[arg(long)]
+        genre: Option<String>,
+        #[command(flatten)]
+        features: features::FeatureFilter,
+        /// Shuffled when reviewing every song, in playlist order otherwise
+        #[arg(long, value_enum)]
+        order: Option<review::Order>,
+        /// Resume this review session, each playlist and `all` have their own by default
+        #[arg(long)]
+        session: Option<String>,
+        /// Start the review session over with a new shuffle
+        #[arg(long)]
+        reshuffle: bool,
+        /// Hide the songs removed from their playlist instead of greying them out
+        #[arg(long)]
+        hide_removed: bool,
+        /// Only review the songs with this tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// Only review the songs whose notes contain this text
+        #[arg(long)]
+        notes: Option<String>,
+    },
+    /// Export the synced songs
+    Export {
+        #[arg(long, value_enum, default_value_t = export::Format::Csv)]
+        format: export::Format,
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Manage the playlists and library sources pulled by `sync`
+    Playlist {
+        #[command(subcommand)]
+        command: PlaylistCommand,
+    },
+    /// Print a summary of the database content
+    Stats,
+    /// Database maintenance
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
 }
 
-async fn ui(
-    term: Arc<Mutex<Terminal<CrosstermBackend<Stdout>>>>,
-    mut rx: tokio::sync::watch::Receiver<App>,
-    mut update_rx: tokio::sync::watch::Receiver<bool>,
-    mut states: Arc<Mutex<States>>,
-) {
-    let mut state = rx.borrow().to_owned();
-    // let mut spt_state = StatefulList::with_items(vec![]);
-    // let mut states = Arc::new(Mutex::new(States { spt_list: spt_state }));
-    loop {
-        select! {
-            _ = rx.changed() => state = rx.borrow().to_owned(),
-            _ = update_rx.changed() => {},
-        }
-        let mut terminal = term.lock().await;
-        let mut states_lck = states.lock().await;
-        draw(&mut terminal, &state, &mut states_lck).unwrap();
-    }
+#[derive(Debug, Subcommand)]
+enum PlaylistCommand {
+    /// Register a playlist by ID, URI or open.spotify.com URL, or one of
+    /// `liked-songs`, `saved-albums` and `followed-artists`
+    Add { playlist: String },
+    /// Stop syncing a playlist or library source
+    Remove { playlist: String },
+    /// List the registered playlists
+    List,
 }
 
-#[derive(Debug, Clone, Copy)]
-enum StreamStatus {
-    Play,
+#[derive(Debug, Clone, Copy, Subcommand)]
+enum DbCommand {
+    /// Apply pending migrations
+    Migrate,
 }
 
-async fn stream_and_play_mp3(mp3_url: String, mut rx: tokio::sync::watch::Receiver<StreamStatus>, stream_handle: rodio::OutputStreamHandle) {
-    struct Reader<R>(futures_util::io::BufReader<R>);
-
-    impl<R: futures_util::AsyncRead + std::marker::Unpin> Read for Reader<R> {
-        fn read(&mut self, mut buf: &mut [u8]) -> io::Result<usize> {
-            use futures::executor;
-            executor::block_on(async {
-                self.0.read(&mut buf).await
-            })
-        }
-    }
-    impl<R: futures_util::AsyncRead> std::io::Seek for Reader<R> {
-        fn seek(&mut self, _pos: io::SeekFrom) -> io::Result<u64> {
-            unimplemented!()
-        }
-    }
-
-    impl<R: futures_util::AsyncRead + std::marker::Unpin + std::marker::Send + std::marker::Sync> MediaSource for Reader<R> {
-        fn is_seekable(&self) -> bool {
-            false
-        }
-
-        fn byte_len(&self) -> Option<u64> {
-            None
-        }
-    }
-    
-    use symphonia::core::io::MediaSourceStream;
-
-    let sink = Sink::try_new(&stream_handle).unwrap();
-    while rx.changed().await.is_ok() {
-        let status = rx.borrow().clone();
-        match status {
-            StreamStatus::Play => {
-                if !sink.empty() {
-                    sink.stop();
-                    continue
-                }
-                let response = reqwest::get(&mp3_url).await.unwrap();
-                let stream = response.bytes_stream().map_err(|e| futures::io::Error::new(futures::io::ErrorKind::Other, e)).into_async_read();
-                let reader = Reader(futures_util::io::BufReader::new(stream));
-                let mss = MediaSourceStream::new(Box::new(reader), Default::default());
-                let decoder = tokio::task::spawn_blocking(|| {
-                    symphonia_decoder::SymphoniaDecoder::new(mss, Some("mp3")).unwrap()
-                }).await.unwrap();
-                sink.append(decoder);
-            },
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
+    let conn = db::connect(&cli.db).await?;
+    // Only `db migrate` changes the schema, an older exospot may still use the database
+    if !matches!(cli.command, Some(Command::Db { .. } | Command::Login { .. })) {
+        let pending = db::pending(&conn).await?;
+        if !pending.is_empty() {
+            anyhow::bail!(
+                "database {} needs {} migrations, run `exospot db migrate` first",
+                cli.db.display(),
+                pending.len()
+            );
         }
     }
-}
 
-#[derive(Clone)]
-struct StatefulList<T> {
-    state: ListState,
-    items: Vec<T>,
-}
-
-impl<T> StatefulList<T> {
-    fn with_items(items: Vec<T>) -> StatefulList<T> {
-        StatefulList {
-            state: ListState::default(),
-            items,
+    match cli.command.unwrap_or(Command::Review {
+        playlist: None,
+        genre: None,
+        features: Default::default(),
+        order: None,
+        session: None,
+        reshuffle: false,
+        hide_removed: false,
+        tag: None,
+        notes: None,
+    }) {
+        Command::Login { auth_url } => {
+            let config = rspotify::Config {
+                auth_base_url: auth_url,
+                cache_path: cli.token_cache,
+                ..Default::default()
+            };
+            auth::login(config).await?
         }
-    }
-
-    fn next(&mut self) {
-        let i = match self.state.selected() {
-            Some(i) => {
-                if i >= self.items.len() - 1 {
-                    0
-                } else {
-                    i + 1
-                }
-            }
-            None => 0,
-        };
-        self.state.select(Some(i));
-    }
-
-    fn previous(&mut self) {
-        let i = match self.state.selected() {
-            Some(i) => {
-                if i == 0 {
-                    self.items.len() - 1
-                } else {
-                    i - 1
+        Command::Sync {
+            full,
+            prune,
+            concurrency,
+            audio_features,
+            covers,
+            max_retries,
+            api_url,
+            auth_url,
+        } => {
+            let options = sync::SyncOptions {
+                full,
+                prune,
+                concurrency,
+                audio_features,
+                covers,
+                retry: retry::RetryPolicy {
+                    max_retries,
+                    ..Default::default()
+                },
+            };
+            let config = rspotify::Config {
+                api_base_url: api_url,
+                auth_base_url: auth_url,
+                cache_path: cli.token_cache,
+                ..Default::default()
+            };
+            // The user login also reads private playlists, app credentials only public ones
+            match auth::user_client(config.clone(), &options.retry).await? {
+                Some(user) => sync::sync_from_spotify(&conn, &user, Some(&user), options).await?,
+                None => {
+                    let spotify = sync::client_credentials(config, &options.retry).await?;
+                    sync::sync_from_spotify(&conn, &spotify, None, options).await?
                 }
             }
-            None => 0,
-        };
-        self.state.select(Some(i));
-    }
-
-    fn unselect(&mut self) {
-        self.state.select(None);
-    }
-}
-
-#[tokio::main]
-async fn main() {
-    // Restore terminal on panic
-    let default_panic = std::panic::take_hook();
-    std::panic::set_hook(Box::new(move |info| {
-        let mut terminal = setup_terminal().unwrap();
-        default_panic(info);
-        restore_terminal(&mut terminal).unwrap();
-    }));
-
-    // States init
-    let mut spt_state = StatefulList::with_items(vec![]);
-    let mut states = Arc::new(Mutex::new(States { spt_list: spt_state }));
-
-    // TUI
-    let  terminal = setup_terminal().unwrap();
-    let  app_state: App = App::Welcome;
-    let (tx, rx) = tokio::sync::watch::channel(app_state.clone());
-    let (input_tx, mut input_rx) = tokio::sync::mpsc::channel(8);
-    let (update_tx, update_rx) = tokio::sync::watch::channel(true);
-    let terminal = Arc::new(Mutex::new(terminal));
-    let task = tokio::task::spawn(ui(terminal.clone(), rx, update_rx, states.clone()));
-    let input_task = tokio::task::spawn(input(input_tx, update_tx, states.clone()));
-
-    let term = terminal.clone();
-    tokio::spawn(async move {
-        select! {
-            _ = task => {},
-            _ = input_task => {}
         }
-        let mut terminal = term.lock().await;
-        restore_terminal(&mut terminal).unwrap();
-        exit(0);
-    });
-
-    // SQL pool
-    let database_url = "sqlite://songs.db";
-    let conn = SqlitePool::connect(database_url).await.unwrap();
-    sqlx::migrate!().run(&conn).await.unwrap();
-    // sync_from_spotify(&conn).await;
-
-    let spt_songs = sqlx::query!("select * from spt_songs ORDER BY RANDOM()")
-        .fetch_all(&conn)
-        .await
-        .unwrap();
-    {
-        let mut lock = states.lock().await;
-        lock.spt_list.items = spt_songs.iter().map(|song| {
-            (song.title.clone(), Color::White)
-        }).collect();
-        lock.spt_list.next();
-    }
-    for song in spt_songs {
-        let artists = sqlx::query!(
-            "SELECT spt_artists.name, spt_artists.id
-            FROM spt_songs_spt_artists
-            INNER JOIN spt_songs ON spt_songs_spt_artists.spt_song_id = spt_songs.id
-            INNER JOIN spt_artists ON spt_songs_spt_artists.spt_artist_id = spt_artists.id
-            WHERE spt_song_id = ?",
-            song.id
-        )
-        .fetch_all(&conn)
-        .await
-        .unwrap();
-        let album = sqlx::query!("SELECT * FROM spt_albums WHERE id = ?", song.album)
-            .fetch_one(&conn)
-            .await
-            .unwrap();
-        let image = sqlx::query!(
-            "SELECT URL FROM spt_albums_covers WHERE album_id = ? ORDER BY height DESC",
-            song.album
-        )
-        .fetch_one(&conn)
-        .await
-        .unwrap();
-        let img_buf = reqwest::get(image.url)
-            .await
-            .unwrap()
-            .bytes()
-            .await
-            .unwrap();
-        
-        let items = StatefulList::with_items(vec![
-            ("Item0".to_owned(), Color::White),
-            ("Item1".to_owned(), Color::White),
-            ("Item2".to_owned(), Color::White),
-            ("Item3".to_owned(), Color::White),
-            ("Item4".to_owned(), Color::White),
-            ("Item5".to_owned(), Color::White),
-            ("Item6".to_owned(), Color::White),
-            ("Item7".to_owned(), Color::White),
-            ("Item8".to_owned(), Color::White),
-            ("Item9".to_owned(), Color::White)]);
-
-        let state: StatefulList<(String, Color)> = items;
-        let app_state = App::Spotify((SpotifyUi {
-            title: song.title.to_owned(),
-            artist: song.artist.to_owned(),
-            cover_img: img_buf,
-            album_name: album.name.to_owned(),
-            album_kind: album.kind.to_owned(),
-            duration: Duration::from_millis(song.duration as u64)
-        }, vec!["salut".to_owned(); 20], state));
-        let url = song.preview_url;
-        tx.send(app_state).unwrap();
-
-        
-
-        let (preview_tx, preview_rx) = tokio::sync::watch::channel(StreamStatus::Play);
-        let (_stream, stream_handle) = rodio::OutputStream::try_default().unwrap();
-        if let Some(url) = url.clone() {
-            tokio::task::spawn(stream_and_play_mp3(url, preview_rx, stream_handle));
+        Command::Review {
+            playlist,
+            genre,
+            features,
+            order,
+            session,
+            reshuffle,
+            hide_removed,
+            tag,
+            notes,
+        } => {
+            let source = playlist.as_deref().map(playlists::Source::parse).transpose()?;
+            let queue = review::Queue {
+                playlist: source.as_ref().map(|source| source.id()),
+                genre: genre.as_deref(),
+                features,
+                order,
+                hide_removed,
+                tag: tag.as_deref(),
+                notes: notes.as_deref(),
+                session: session.as_deref(),
+                reshuffle,
+            };
+            review::run(&conn, queue).await?
         }
-
-        'outer: loop {
-            select! {
-                Some(msg) = input_rx.recv() => {
-                    let Event::Key(key) = msg else { continue };
-                    match key.code {
-                        KeyCode::Enter => {
-                            let mut lock = states.lock().await;
-                            lock.spt_list.next();
-                            let i = lock.spt_list.state.selected().unwrap();
-                            lock.spt_list.items.get_mut(i).unwrap().1 = Color::Green;
-                            break 'outer
-                        },
-                        KeyCode::Char('y') => { open::that(format!("https://www.youtube.com/results?search_query={}", urlencoding::encode(&format!("{} {}", song.artist, song.title))).to_string()).unwrap(); }
-                        KeyCode::Char('p') | KeyCode::Char(' ') => { if let Some(_) = url { preview_tx.send(StreamStatus::Play).unwrap() }}
-                        _ => {}
-                    }
-                }
+        Command::Export { format, output } => export::export(&conn, format, output.as_deref()).await?,
+        Command::Playlist { command } => match command {
+            PlaylistCommand::Add { playlist } => playlists::add(&conn, &playlist).await?,
+            PlaylistCommand::Remove { playlist } => playlists::remove(&conn, &playlist).await?,
+            PlaylistCommand::List => playlists::list(&conn).await?,
+        },
+        Command::Stats => stats::print_stats(&conn).await?,
+        Command::Db {
+            command: DbCommand::Migrate,
+        } => {
+            let pending = db::pending(&conn).await?;
+            db::migrate(&conn).await?;
+            for description in &pending {
+                println!("Applied {description}");
             }
+            println!("Database {} is up to date", cli.db.display())
         }
-        // println!("Titre: {}", song.title);
-        // println!("Artiste: {}", song.artist);
-        // println!("Artistes: {:?}", artists);
-        // println!("ID: {}", song.id);
-        // println!("Album: {}", song.album);
-        // println!("Album name: {}", album.name);
-        // println!("Album type: {}", album.kind);
-
-        // println!("Biggest cover image url: {}", image.url);
     }
 
     conn.close().await;
-    let mut terminal = terminal.lock().await;
-    restore_terminal(&mut terminal).unwrap();
-}
-
-async fn sync_from_spotify(conn: &sqlx::SqlitePool) {
-    let creds = Credentials::from_env().unwrap();
-    let spotify = ClientCredsSpotify::new(creds);
-    spotify.request_token().await.unwrap();
-
-    let playlist = spotify.playlist_items(
-        PlaylistId::from_id("2qv1rmsLVKtnk3n9oLj3vb").unwrap(),
-        None,
-        None,
-    );
-
-    let mut ids = std::collections::HashSet::new();
-    let data = std::sync::Arc::new(std::sync::Mutex::new(ids));
-
-    playlist.try_for_each_concurrent(10, |item| async {
-        if let Some(playable) = item.track {
-            if let PlayableItem::Track(track) = playable {
-                // dbg!(&track);
-                let id = track.id.clone().unwrap().id().to_owned();
-                let title = track.name.to_owned();
-                let artist = track.artists.first().unwrap().name.to_owned();
-                let album_id = track.album.id.unwrap().to_string();
-                let album_type = track.album.album_type.unwrap();
-                let duration_ms = track.duration.num_milliseconds();
-                let preview_url = track.preview_url;
-
-                // Check if the song is already in the db
-                if let Some(_) = sqlx::query!("SELECT id FROM spt_songs where id = $1", id).fetch_optional(conn).await.unwrap() {
-                    return Ok(())
-                }
-
-                if let Ok(_) = sqlx::query!("INSERT INTO spt_albums(id, name, kind) VALUES ($1, $2, $3)", album_id, track.album.name, album_type).execute(conn).await {
-                    for image in &track.album.images {
-                        sqlx::query!("INSERT INTO spt_albums_covers(album_id, url, height, width) VALUES ($1, $2, $3, $4)",
-                        album_id, image.url, image.height, image.width).execute(conn).await;
-                    }
-                }
-                let rnd = rand::random::<[u8; 8]>();
-                let rnd = base64::engine::general_purpose::STANDARD_NO_PAD.encode(rnd);
-                // dbg!(&rnd);
-                sqlx::query!("INSERT INTO songs(id) VALUES ($1)", rnd).execute(conn).await.unwrap();
-                if let Ok(_) = sqlx::query!(
-                    "INSERT INTO spt_songs(id, title, artist, album, duration, preview_url, song) VALUES ($1, $2, $3, $4, $5, $6, $7)",
-                    id,
-                    title,
-                    artist,
-                    album_id,
-                    duration_ms,
-                    preview_url,
-                    rnd
-                ).execute(conn).await {
-                    for i in &track.artists {
-                        let a = &i.id.clone().unwrap().to_string();
-                        sqlx::query!(
-                            "INSERT INTO spt_artists(id, name) VALUES ($1, $2)",
-                            a,
-                            i.name
-                        )
-                        .execute(conn)
-                        .await;
-                        sqlx::query!("INSERT INTO spt_songs_spt_artists(spt_song_id, spt_artist_id) VALUES ($1, $2)", id, a).execute(conn).await.unwrap();
-                    }
-                }
-                let mut ids = data.lock().unwrap();
-                if !ids.insert(id.to_owned()) {
-                    println!("{}    {}      {}", id, title, artist)
-                }
-            }
-        }
-        Ok(())
-    }).await.unwrap();
+    Ok(())
 }
@@ -0,0 +1,65 @@
+use sqlx::SqlitePool;
+
+use crate::search;
+
+/// What the user wrote about a song, `songs.id`, while reviewing it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Annotations {
+    pub notes: Option<String>,
+    /// Sorted by name
+    pub tags: Vec<String>,
+}
+
+pub async fn get(conn: &SqlitePool, song_id: &str) -> anyhow::Result<Annotations> {
+    let notes = sqlx::query_scalar!("SELECT notes FROM songs WHERE id = $1", song_id)
+        .fetch_optional(conn)
+        .await?
+        .flatten();
+    let tags = sqlx::query_scalar!("SELECT tag FROM songs_tags WHERE song_id = $1 ORDER BY tag", song_id)
+        .fetch_all(conn)
+        .await?;
+    Ok(Annotations { notes, tags })
+}
+
+/// Replaces the notes of a song, blank notes clear them.
+pub async fn set_notes(conn: &SqlitePool, song_id: &str, notes: &str) -> sqlx::Result<()> {
+    let notes = Some(notes.trim()).filter(|notes| !notes.is_empty());
+    sqlx::query!("UPDATE songs SET notes = $1 WHERE id = $2", notes, song_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Comma separated tags, trimmed and lowercased, like `for set B, needs edit`.
+pub fn parse_tags(input: &str) -> Vec<String> {
+    let mut tags: Vec<_> = input
+        .split(',')
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// Replaces the tags of a song, in the search index too, and forgets the tags
+/// no song uses anymore.
+pub async fn set_tags(conn: &SqlitePool, song_id: &str, tags: &[String]) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+    sqlx::query!("DELETE FROM songs_tags WHERE song_id = $1", song_id)
+        .execute(&mut *tx)
+        .await?;
+    for tag in tags {
+        sqlx::query!("INSERT OR IGNORE INTO tags(name) VALUES ($1)", tag)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("INSERT OR IGNORE INTO songs_tags(song_id, tag) VALUES ($1, $2)", song_id, tag)
+            .execute(&mut *tx)
+            .await?;
+    }
+    sqlx::query!("DELETE FROM tags WHERE name NOT IN (SELECT tag FROM songs_tags)")
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    search::reindex_tags(conn, song_id).await
+}
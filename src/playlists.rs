@@ -0,0 +1,136 @@
+use anyhow::{anyhow, Context};
+use rspotify::model::{Id, PlaylistId};
+use sqlx::SqlitePool;
+
+/// Something `sync` pulls tracks from: a playlist or a part of the user
+/// library, which needs `exospot login`. Library sources are stored in
+/// `spt_playlists` under a reserved ID that cannot collide with a playlist ID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Playlist(PlaylistId<'static>),
+    LikedSongs,
+    SavedAlbums,
+    FollowedArtists,
+}
+
+impl Source {
+    /// Accepts `liked-songs`, `saved-albums`, `followed-artists` or anything
+    /// [`parse_playlist_id`] does.
+    pub fn parse(input: &str) -> anyhow::Result<Self> {
+        match Self::library(input.trim()) {
+            Some(source) => Ok(source),
+            None => Ok(Source::Playlist(parse_playlist_id(input)?)),
+        }
+    }
+
+    /// The library source stored in `spt_playlists` under this ID, if any.
+    pub fn library(id: &str) -> Option<Self> {
+        match id {
+            "liked-songs" => Some(Source::LikedSongs),
+            "saved-albums" => Some(Source::SavedAlbums),
+            "followed-artists" => Some(Source::FollowedArtists),
+            _ => None,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        match self {
+            Source::Playlist(id) => id.id(),
+            Source::LikedSongs => "liked-songs",
+            Source::SavedAlbums => "saved-albums",
+            Source::FollowedArtists => "followed-artists",
+        }
+    }
+
+    /// Display name of a library source, playlists get theirs from Spotify.
+    pub fn library_name(&self) -> Option<&'static str> {
+        match self {
+            Source::Playlist(_) => None,
+            Source::LikedSongs => Some("Liked Songs"),
+            Source::SavedAlbums => Some("Saved albums"),
+            Source::FollowedArtists => Some("Followed artists"),
+        }
+    }
+}
+
+/// Accepts a bare playlist ID, a `spotify:playlist:` URI or an
+/// `https://open.spotify.com/playlist/...` URL.
+pub fn parse_playlist_id(input: &str) -> anyhow::Result<PlaylistId<'static>> {
+    let input = input.trim();
+    let id = match input.split_once("open.spotify.com/") {
+        Some((_, path)) => {
+            let path = path.split(['?', '#']).next().unwrap_or_default();
+            // Localized links look like open.spotify.com/intl-fr/playlist/<id>
+            let id = path
+                .split('/')
+                .skip_while(|s| *s != "playlist")
+                .nth(1)
+                .filter(|id| !id.is_empty())
+                .ok_or_else(|| anyhow!("{input} is not a playlist URL"))?;
+            PlaylistId::from_id(id)
+        }
+        None => PlaylistId::from_id_or_uri(input),
+    };
+    Ok(id.with_context(|| format!("invalid playlist ID {input}"))?.into_static())
+}
+
+pub async fn add(conn: &SqlitePool, input: &str) -> anyhow::Result<()> {
+    let source = Source::parse(input)?;
+    let id = source.id();
+    let name = source.library_name();
+    let added = sqlx::query!("INSERT OR IGNORE INTO spt_playlists(id, name) VALUES ($1, $2)", id, name)
+        .execute(conn)
+        .await?
+        .rows_affected();
+    if added == 0 {
+        println!("Playlist {id} is already registered");
+    } else {
+        println!("Registered playlist {id}");
+    }
+    Ok(())
+}
+
+pub async fn remove(conn: &SqlitePool, input: &str) -> anyhow::Result<()> {
+    let source = Source::parse(input)?;
+    let id = source.id();
+    let mut tx = conn.begin().await?;
+    sqlx::query!("DELETE FROM spt_songs_spt_playlists WHERE spt_playlist_id = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM spt_sync_progress_songs WHERE spt_playlist_id = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM spt_sync_progress WHERE spt_playlist_id = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    let removed = sqlx::query!("DELETE FROM spt_playlists WHERE id = $1", id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    tx.commit().await?;
+    if removed == 0 {
+        return Err(anyhow!("playlist {id} is not registered"));
+    }
+    println!("Removed playlist {id}");
+    Ok(())
+}
+
+pub async fn list(conn: &SqlitePool) -> anyhow::Result<()> {
+    let playlists = sqlx::query!(
+        "SELECT spt_playlists.id, spt_playlists.name,
+            (SELECT count(*) FROM spt_songs_spt_playlists WHERE spt_playlist_id = spt_playlists.id AND removed_at IS NULL) AS \"songs!: i64\"
+        FROM spt_playlists
+        ORDER BY spt_playlists.name"
+    )
+    .fetch_all(conn)
+    .await?;
+    for playlist in playlists {
+        println!(
+            "{}  {:>6} songs  {}",
+            playlist.id,
+            playlist.songs,
+            playlist.name.as_deref().unwrap_or("(never synced)")
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use std::{future::Future, time::Duration};
+
+use reqwest::{header::RETRY_AFTER, StatusCode};
+use rspotify::{http::HttpError, ClientError, ClientResult};
+
+/// Retries Spotify Web API calls that failed because of rate limiting (429)
+/// or a transient server or network error, waiting for `Retry-After` when the
+/// API sends it and backing off exponentially otherwise. A `Retry-After`
+/// longer than `max_delay` is not waited for, the request fails instead.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 5,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub async fn run<T, F, Fut>(&self, mut request: F) -> ClientResult<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = ClientResult<T>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match request().await {
+                Err(err) if attempt < self.max_retries => {
+                    let Some(delay) = self.delay(&err, attempt) else {
+                        return Err(err);
+                    };
+                    eprintln!("Spotify request failed ({err}), retrying in {}s", delay.as_secs_f32());
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// How long to wait before retrying, `None` if the error is not worth a retry.
+    fn delay(&self, err: &ClientError, attempt: u32) -> Option<Duration> {
+        let backoff = self
+            .base_delay
+            .saturating_mul(2_u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let ClientError::Http(err) = err else {
+            return None;
+        };
+        match err.as_ref() {
+            HttpError::StatusCode(response) if response.status() == StatusCode::TOO_MANY_REQUESTS => {
+                let retry_after = response
+                    .headers()
+                    .get(RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.trim().parse().ok())
+                    .map(Duration::from_secs);
+                // Spotify can ask for hours, better fail and sync again later
+                retry_after.map_or(Some(backoff), |delay| (delay <= self.max_delay).then_some(delay))
+            }
+            HttpError::StatusCode(response) if response.status().is_server_error() => Some(backoff),
+            HttpError::Client(err) if err.is_timeout() || err.is_connect() => Some(backoff),
+            _ => None,
+        }
+    }
+}
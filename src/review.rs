@@ -0,0 +1,855 @@
+use clap::ValueEnum;
+use crossterm::{
+    event::{Event, EventStream, KeyCode},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use futures_util::StreamExt;
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    layout::Rect,
+    widgets::{self as tui_widgets, Block, Borders, List, ListItem, ListState, Paragraph},
+    Terminal,
+};
+use ratatu_image::picker::{BackendType, Picker};
+use rodio::{Sink, Source};
+use sqlx::SqlitePool;
+use std::{
+    collections::{HashSet, VecDeque},
+    error::Error,
+    io::{self, Stdout},
+    process::exit,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::{select, sync::Mutex};
+
+use crate::{
+    covers,
+    decisions::{self, ReviewStatus},
+    features::{AudioFeatures, FeatureFilter},
+    http_source::HttpSource,
+    notes, search, sessions, symphonia_decoder, widgets,
+};
+
+fn setup_terminal() -> Result<Terminal<CrosstermBackend<Stdout>>, Box<dyn Error>> {
+    let mut stdout = io::stdout();
+    enable_raw_mode()?;
+    execute!(stdout, EnterAlternateScreen)?;
+    Ok(Terminal::new(CrosstermBackend::new(stdout))?)
+}
+
+fn restore_terminal(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+) -> Result<(), Box<dyn Error>> {
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen,)?;
+    Ok(terminal.show_cursor()?)
+}
+
+#[derive(Debug, Clone)]
+enum App {
+    Welcome,
+    Spotify(Box<SpotifyUi>),
+}
+
+struct States {
+    spt_list: StatefulList<(String, Color)>,
+    /// Items matching the `/` search, in list order, every item is shown when `None`
+    matches: Option<Vec<usize>>,
+    cover: Option<widgets::spotify::Cover>,
+}
+
+impl States {
+    /// Indices of the items shown in the list.
+    fn visible(&self) -> Vec<usize> {
+        match &self.matches {
+            Some(matches) => matches.clone(),
+            None => (0..self.spt_list.items.len()).collect(),
+        }
+    }
+
+    /// Highlights the item `index`, nothing when it is filtered out.
+    fn select(&mut self, index: usize) {
+        let position = self.visible().iter().position(|item| *item == index);
+        self.spt_list.state.select(position);
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpotifyUi {
+    pub title: String,
+    pub artist: String,
+    pub album_name: String,
+    pub album_kind: String,
+    pub release_year: Option<i32>,
+    pub duration: Duration,
+    pub audio_features: Option<AudioFeatures>,
+    /// From 1 to 5 stars
+    pub rating: Option<i64>,
+    pub notes: Option<String>,
+    pub tags: Vec<String>,
+    /// Text input shown over the song while editing its notes or tags
+    pub popup: Option<Popup>,
+    /// Shown under the title, like an error that did not stop the review
+    pub message: Option<String>,
+}
+
+/// What the text input popup edits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Notes,
+    /// Comma separated
+    Tags,
+    /// Filters the list as you type
+    Search,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Popup {
+    pub field: Field,
+    pub input: String,
+}
+
+/// Order of the review queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Order {
+    /// Playlist order, needs `--playlist`
+    Playlist,
+    /// Same shuffle at every launch until `--reshuffle`
+    Shuffle,
+    /// First added to a playlist first
+    AddedDate,
+    /// By artist, then album and track number
+    Artist,
+    /// By album name and track number
+    Album,
+    /// Shortest first
+    Duration,
+    /// Oldest release first, songs of the same year stay shuffled or in playlist order
+    ReleaseYear,
+    /// Slowest first, songs without audio features last
+    Tempo,
+    /// Calmest first, songs without audio features last
+    Energy,
+    /// Least danceable first, songs without audio features last
+    Danceability,
+}
+
+/// How many decisions `u` can take back.
+const UNDO_DEPTH: usize = 100;
+
+/// A cover download that stalls longer than this leaves the placeholder.
+const COVER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Cell size in pixels when the terminal does not tell it.
+const DEFAULT_FONT_SIZE: (u16, u16) = (10, 20);
+
+/// How many songs PageUp and PageDown move by.
+const PAGE_SIZE: usize = 10;
+
+/// What ends the review of the current song.
+enum Action {
+    Decide(ReviewStatus),
+    /// Take back the last decision and go back to its song
+    Undo,
+    /// Load another song of the list without deciding on this one
+    Jump(usize),
+    Quit,
+}
+
+/// A decision and what the song review was before it.
+struct Decision {
+    index: usize,
+    song_id: String,
+    previous: Option<decisions::Review>,
+}
+
+fn status_color(status: ReviewStatus, removed: bool) -> Color {
+    match status {
+        ReviewStatus::Pending if removed => Color::DarkGray,
+        ReviewStatus::Pending => Color::White,
+        ReviewStatus::Later => Color::Yellow,
+        ReviewStatus::Kept => Color::Green,
+        ReviewStatus::Rejected => Color::Red,
+    }
+}
+
+/// Song of the list a navigation key moves to from `index`.
+pub fn navigate(key: KeyCode, index: usize, len: usize) -> Option<usize> {
+    let last = len.checked_sub(1)?;
+    match key {
+        KeyCode::Up => Some(index.saturating_sub(1)),
+        KeyCode::Down => Some((index + 1).min(last)),
+        KeyCode::PageUp => Some(index.saturating_sub(PAGE_SIZE)),
+        KeyCode::PageDown => Some((index + PAGE_SIZE).min(last)),
+        KeyCode::Home | KeyCode::Char('g') => Some(0),
+        KeyCode::End | KeyCode::Char('G') => Some(last),
+        _ => None,
+    }
+}
+
+/// The search match after `index` in the list, or before it when `backwards`,
+/// wrapping around.
+pub fn next_match(matches: &[usize], index: usize, backwards: bool) -> Option<usize> {
+    let found = if backwards {
+        matches.iter().rev().find(|item| **item < index).or(matches.last())
+    } else {
+        matches.iter().find(|item| **item > index).or(matches.first())
+    };
+    found.copied()
+}
+
+/// Year of a Spotify release date, whatever its precision.
+pub fn release_year(release_date: &str) -> Option<i32> {
+    release_date.get(..4)?.parse().ok()
+}
+
+/// The song list and the song details.
+fn panes(area: Rect) -> Rc<[Rect]> {
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(20), Constraint::Percentage(80)].as_ref())
+        .split(area)
+}
+
+fn draw(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    app: &App,
+    states: &mut States,
+) -> Result<(), Box<dyn Error>> {
+    terminal.draw(|frame| {
+        match app {
+            App::Welcome => {
+                let greeting = Paragraph::new("Welcome to Exospot");
+                frame.render_widget(greeting, frame.size());
+            }
+            App::Spotify(spt_ui) => {
+                let spt_widget = widgets::spotify::Clear(spt_ui.as_ref().clone());
+                let chunks = panes(frame.size());
+                frame.render_stateful_widget(spt_widget, chunks[1], &mut states.cover);
+
+                let items: Vec<_> = states.visible().into_iter().map(|index| {
+                    let song = &states.spt_list.items[index];
+                    ListItem::new(Line::from(Span::raw(&song.0))).style(Style::default().fg(song.1))
+                }).collect();
+                let list = List::new(items)
+                    .block(Block::default().title("List").borders(Borders::ALL))
+                    .highlight_style(
+                        Style::default()
+                            .bg(Color::LightGreen)
+                            .fg(Color::DarkGray)
+                            .add_modifier(Modifier::BOLD),
+                    )
+                    .highlight_symbol(">>");
+                frame.render_stateful_widget(list, chunks[0], &mut states.spt_list.state);
+
+                if let Some(popup) = &spt_ui.popup {
+                    let title = match popup.field {
+                        Field::Notes => "Notes (Entrée pour enregistrer, Échap pour annuler)".to_owned(),
+                        Field::Tags => "Tags séparés par des virgules (Entrée pour enregistrer, Échap pour annuler)".to_owned(),
+                        Field::Search => format!(
+                            "Recherche, {} résultats (Entrée pour valider, Échap pour annuler)",
+                            states.matches.as_ref().map_or(states.spt_list.items.len(), Vec::len)
+                        ),
+                    };
+                    let area = Rect {
+                        x: chunks[1].x + chunks[1].width / 10,
+                        y: chunks[1].y + chunks[1].height * 3 / 5,
+                        width: chunks[1].width * 8 / 10,
+                        height: 3.min(chunks[1].height),
+                    };
+                    let input = Paragraph::new(format!("{}_", popup.input))
+                        .block(Block::default().title(title).borders(Borders::ALL));
+                    frame.render_widget(tui_widgets::Clear, area);
+                    frame.render_widget(input, area);
+                }
+            }
+        }
+    })?;
+    Ok(())
+}
+
+pub trait DisplayTimestamp {
+    fn display_timestamp(&self) -> Result<String, anyhow::Error>;
+}
+
+impl DisplayTimestamp for chrono::Duration {
+    fn display_timestamp(&self) -> Result<String, anyhow::Error> {
+        let mut a = *self;
+        let minutes = a.num_minutes();
+        a = a - chrono::Duration::from_std(std::time::Duration::from_secs((a.num_minutes()*60) as u64))?;
+        let seconds = a.num_seconds();
+        Ok(format!("{minutes:0>2}:{seconds:0>2}"))
+    }
+}
+
+async fn input(
+    tx: tokio::sync::mpsc::Sender<Event>,
+    update_tx: tokio::sync::watch::Sender<bool>,
+) {
+    let mut reader = EventStream::new();
+    loop {
+        let event = reader.next().await;
+        let Some(event) = event else { continue };
+        let Ok(event) = event else { continue };
+        match event {
+            Event::FocusGained => {}
+            Event::FocusLost => {}
+            Event::Key(_) => {}
+            Event::Mouse(_) => {}
+            Event::Paste(_) => {}
+            Event::Resize(_, _) => {
+                if update_tx.send(true).is_err() {
+                    break;
+                }
+            }
+        }
+        // The review is over
+        if tx.send(event).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn ui(
+    term: Arc<Mutex<Terminal<CrosstermBackend<Stdout>>>>,
+    mut rx: tokio::sync::watch::Receiver<App>,
+    mut update_rx: tokio::sync::watch::Receiver<bool>,
+    states: Arc<Mutex<States>>,
+) {
+    let mut state = rx.borrow().to_owned();
+    loop {
+        // The review or the input task is over
+        select! {
+            changed = rx.changed() => match changed {
+                Ok(()) => state = rx.borrow().to_owned(),
+                Err(_) => break,
+            },
+            changed = update_rx.changed() => if changed.is_err() {
+                break;
+            },
+        }
+        let mut terminal = term.lock().await;
+        let mut states_lck = states.lock().await;
+        draw(&mut terminal, &state, &mut states_lck).unwrap();
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum StreamStatus {
+    Play,
+    /// Moves the preview this many seconds forward, backward when negative
+    Skip(i64),
+}
+
+/// How far the arrows move in the preview
+const SKIP_SECONDS: i64 = 5;
+/// The preview is read and seeked on the audio thread, a stalled download
+/// fails after this instead of stalling the audio output
+const PREVIEW_TIMEOUT: Duration = Duration::from_secs(5);
+
+async fn stream_and_play_mp3(mp3_url: String, mut rx: tokio::sync::mpsc::UnboundedReceiver<StreamStatus>, stream_handle: rodio::OutputStreamHandle) {
+    use symphonia::core::io::MediaSourceStream;
+
+    let Ok(sink) = Sink::try_new(&stream_handle) else { return };
+    // Seconds to skip, applied by the audio thread between two samples
+    let mut skip = Arc::new(AtomicI64::new(0));
+    while let Some(status) = rx.recv().await {
+        match status {
+            StreamStatus::Skip(seconds) => {
+                skip.fetch_add(seconds, Ordering::Relaxed);
+            }
+            StreamStatus::Play => {
+                if !sink.empty() {
+                    sink.stop();
+                    continue
+                }
+                let url = mp3_url.clone();
+                let decoder = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+                    let client = reqwest::blocking::Client::builder().timeout(PREVIEW_TIMEOUT).build()?;
+                    let source = HttpSource::new(client, &url)?;
+                    let mss = MediaSourceStream::new(Box::new(source), Default::default());
+                    Ok(symphonia_decoder::SymphoniaDecoder::new(mss, Some("mp3"))?)
+                }).await;
+                // Nowhere to report it without breaking the UI, the song stays silent
+                if let Ok(Ok(decoder)) = decoder {
+                    skip = Arc::new(AtomicI64::new(0));
+                    let pending = skip.clone();
+                    sink.append(decoder.periodic_access(Duration::from_millis(50), move |decoder| {
+                        let seconds = pending.swap(0, Ordering::Relaxed);
+                        if seconds != 0 {
+                            let target = decoder.elapsed().as_secs_f64() + seconds as f64;
+                            let target = Duration::from_secs_f64(target.max(0.0));
+                            // A failed seek keeps playing from where it was, or ends the song
+                            let _ = decoder.seek(decoder.total_duration().map_or(target, |total| target.min(total)));
+                        }
+                    }));
+                }
+            },
+        }
+    }
+}
+
+#[derive(Clone)]
+struct StatefulList<T> {
+    state: ListState,
+    items: Vec<T>,
+}
+
+impl<T> StatefulList<T> {
+    fn with_items(items: Vec<T>) -> StatefulList<T> {
+        StatefulList {
+            state: ListState::default(),
+            items,
+        }
+    }
+}
+
+/// Which songs are reviewed and in which order.
+#[derive(Debug, Clone, Default)]
+pub struct Queue<'a> {
+    /// Only the songs of this playlist or library source
+    pub playlist: Option<&'a str>,
+    /// Only the songs with an artist whose genre contains this text
+    pub genre: Option<&'a str>,
+    pub features: FeatureFilter,
+    pub order: Option<Order>,
+    pub hide_removed: bool,
+    /// Only the songs with this tag
+    pub tag: Option<&'a str>,
+    /// Only the songs whose notes contain this text
+    pub notes: Option<&'a str>,
+    /// Review session to resume, one per playlist when not set
+    pub session: Option<&'a str>,
+    /// Start the session over with a new shuffle
+    pub reshuffle: bool,
+}
+
+pub async fn run(conn: &SqlitePool, queue: Queue<'_>) -> anyhow::Result<()> {
+    let Queue {
+        playlist,
+        genre,
+        features,
+        order,
+        hide_removed,
+        tag,
+        notes,
+        session,
+        reshuffle,
+    } = queue;
+    if order == Some(Order::Playlist) && playlist.is_none() {
+        return Err(anyhow::anyhow!("--order playlist needs --playlist"));
+    }
+    let order = order.unwrap_or(if playlist.is_some() { Order::Playlist } else { Order::Shuffle });
+    let session = sessions::open(conn, session.or(playlist).unwrap_or("all"), reshuffle).await?;
+
+    // A song is removed when it left the reviewed playlist, or every playlist
+    // when reviewing all of them
+    let spt_songs = sqlx::query!(
+        "SELECT spt_songs.*, spt_albums.name AS album_name, spt_albums.kind AS album_kind, spt_albums.release_date,
+            spt_audio_features.tempo AS \"tempo?\", spt_audio_features.key AS \"key?\", spt_audio_features.mode AS \"mode?\",
+            spt_audio_features.time_signature AS \"time_signature?\", spt_audio_features.energy AS \"energy?\",
+            spt_audio_features.valence AS \"valence?\", spt_audio_features.danceability AS \"danceability?\",
+            spt_audio_features.loudness AS \"loudness?\",
+            (SELECT status FROM song_reviews WHERE song_id = spt_songs.song) AS \"status?: String\",
+            CASE WHEN $1 IS NULL
+                THEN EXISTS (SELECT 1 FROM spt_songs_spt_playlists WHERE spt_song_id = spt_songs.id)
+                    AND NOT EXISTS (SELECT 1 FROM spt_songs_spt_playlists WHERE spt_song_id = spt_songs.id AND removed_at IS NULL)
+                ELSE spt_songs_spt_playlists.removed_at IS NOT NULL
+            END AS \"removed!: bool\",
+            CASE WHEN $1 IS NULL
+                THEN (SELECT min(added_at) FROM spt_songs_spt_playlists WHERE spt_song_id = spt_songs.id)
+                ELSE spt_songs_spt_playlists.added_at
+            END AS \"added_at?: String\"
+        FROM spt_songs
+        INNER JOIN spt_albums ON spt_albums.id = spt_songs.album
+        LEFT JOIN spt_audio_features ON spt_audio_features.spt_song_id = spt_songs.id
+        LEFT JOIN spt_songs_spt_playlists ON spt_songs_spt_playlists.spt_song_id = spt_songs.id
+            AND spt_songs_spt_playlists.spt_playlist_id = $1
+        WHERE ($1 IS NULL OR spt_songs_spt_playlists.spt_playlist_id IS NOT NULL)
+            AND ($2 IS NULL OR EXISTS (
+                SELECT 1 FROM spt_songs_spt_artists
+                INNER JOIN spt_artists_spt_genres ON spt_artists_spt_genres.spt_artist_id = spt_songs_spt_artists.spt_artist_id
+                WHERE spt_songs_spt_artists.spt_song_id = spt_songs.id AND spt_artists_spt_genres.spt_genre LIKE '%' || $2 || '%'
+            ))
+            AND ($3 IS NULL OR EXISTS (SELECT 1 FROM songs_tags WHERE song_id = spt_songs.song AND tag = lower(trim($3))))
+            AND ($4 IS NULL OR (SELECT notes FROM songs WHERE id = spt_songs.song) LIKE '%' || $4 || '%')
+        ORDER BY spt_songs_spt_playlists.position, spt_songs.id",
+        playlist,
+        genre,
+        tag,
+        notes
+    )
+    .fetch_all(conn)
+    .await?;
+    let mut spt_songs: Vec<_> = spt_songs
+        .into_iter()
+        .map(|song| -> anyhow::Result<_> {
+            let audio_features = match (song.tempo, song.key, song.mode, song.time_signature) {
+                (Some(tempo), Some(key), Some(mode), Some(time_signature)) => Some(AudioFeatures {
+                    tempo,
+                    key,
+                    mode,
+                    time_signature,
+                    energy: song.energy.unwrap_or_default(),
+                    valence: song.valence.unwrap_or_default(),
+                    danceability: song.danceability.unwrap_or_default(),
+                    loudness: song.loudness.unwrap_or_default(),
+                }),
+                _ => None,
+            };
+            let status = song.status.as_deref().map(str::parse::<ReviewStatus>).transpose()?;
+            Ok((song, audio_features, status.unwrap_or_default()))
+        })
+        .collect::<anyhow::Result<_>>()?;
+    spt_songs.retain(|(song, audio_features, _)| !(hide_removed && song.removed) && features.matches(audio_features.as_ref()));
+    let by_feature = |feature: fn(&AudioFeatures) -> f64| {
+        move |(_, a, _): &(_, Option<AudioFeatures>, _), (_, b, _): &(_, Option<AudioFeatures>, _)| match (a, b) {
+            (Some(a), Some(b)) => feature(a).total_cmp(&feature(b)),
+            (a, b) => b.is_some().cmp(&a.is_some()),
+        }
+    };
+    // Every song gets the same place in the session shuffle at each launch,
+    // the other orders keep it between equal songs when reviewing every song
+    if order == Order::Shuffle || playlist.is_none() {
+        spt_songs.sort_by_cached_key(|(song, _, _)| sessions::shuffle_key(session.seed, &song.id));
+    }
+    match order {
+        Order::Playlist | Order::Shuffle => {}
+        // Songs without an added date last
+        Order::AddedDate => spt_songs.sort_by_cached_key(|(song, _, _)| (song.added_at.is_none(), song.added_at.clone())),
+        Order::Artist => spt_songs.sort_by_cached_key(|(song, _, _)| {
+            (song.artist.to_lowercase(), song.album_name.to_lowercase(), song.disc_number, song.track_number)
+        }),
+        Order::Album => spt_songs.sort_by_cached_key(|(song, _, _)| {
+            (song.album_name.to_lowercase(), song.disc_number, song.track_number)
+        }),
+        Order::Duration => spt_songs.sort_by_key(|(song, _, _)| song.duration),
+        // Songs without a release date last
+        Order::ReleaseYear => spt_songs.sort_by_key(|(song, _, _)| {
+            song.release_date.as_deref().and_then(release_year).map_or((1, 0), |year| (0, year))
+        }),
+        Order::Tempo => spt_songs.sort_by(by_feature(|features| features.tempo)),
+        Order::Energy => spt_songs.sort_by(by_feature(|features| features.energy)),
+        Order::Danceability => spt_songs.sort_by(by_feature(|features| features.danceability)),
+    }
+    // Unreviewed songs first, the sort is stable so the order above holds within each group
+    spt_songs.sort_by_key(|(_, _, status)| status.queue_rank());
+    // Resume on the song left on screen, if it is still in the queue
+    let mut index = session
+        .current_spt_song_id
+        .as_deref()
+        .and_then(|current| spt_songs.iter().position(|(song, _, _)| song.id == current))
+        .unwrap_or_default();
+
+    // The terminal answers on stdin, so it is asked before the input task reads it
+    let picker = Picker::from_termios(None)
+        .or_else(|_| Picker::new(DEFAULT_FONT_SIZE, BackendType::Halfblocks, None))
+        .map_err(|e| anyhow::anyhow!("{e}"))?;
+    let font_size = picker.font_size();
+
+    // Restore terminal on panic
+    let default_panic = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let mut terminal = setup_terminal().unwrap();
+        default_panic(info);
+        restore_terminal(&mut terminal).unwrap();
+    }));
+
+    // States init
+    let spt_state = StatefulList::with_items(vec![]);
+    let states = Arc::new(Mutex::new(States { spt_list: spt_state, matches: None, cover: None }));
+
+    // TUI
+    let terminal = setup_terminal().map_err(|e| anyhow::anyhow!("{e}"))?;
+    let app_state: App = App::Welcome;
+    let (tx, rx) = tokio::sync::watch::channel(app_state.clone());
+    let tx = Arc::new(tx);
+    let (input_tx, mut input_rx) = tokio::sync::mpsc::channel(8);
+    let (update_tx, update_rx) = tokio::sync::watch::channel(true);
+    let terminal = Arc::new(Mutex::new(terminal));
+    let ui_task = tokio::task::spawn(ui(terminal.clone(), rx, update_rx, states.clone()));
+    let input_task = tokio::task::spawn(input(input_tx, update_tx));
+    let ui_abort = ui_task.abort_handle();
+    let input_abort = input_task.abort_handle();
+
+    // Only a panic ends these tasks before the review
+    let term = terminal.clone();
+    let watcher = tokio::spawn(async move {
+        select! {
+            _ = ui_task => {},
+            _ = input_task => {}
+        }
+        let mut terminal = term.lock().await;
+        restore_terminal(&mut terminal).unwrap();
+        exit(0);
+    });
+
+    // Every error ends the review, the terminal is restored before it is returned
+    let result: anyhow::Result<()> = async {
+        {
+            let mut lock = states.lock().await;
+            lock.spt_list.items = spt_songs.iter().map(|(song, _, status)| {
+                (song.title.clone(), status_color(*status, song.removed))
+            }).collect();
+            lock.select(index);
+        }
+        let mut undo: VecDeque<Decision> = VecDeque::new();
+        // For the next song shown
+        let mut message = None;
+        let http = reqwest::Client::builder().timeout(COVER_TIMEOUT).build()?;
+        // Loads the cover of the song on screen
+        let mut cover_task: Option<tokio::task::JoinHandle<()>> = None;
+        while let Some((song, audio_features, _)) = spt_songs.get(index) {
+            sessions::save_position(conn, &session.name, &song.id).await?;
+            // The smallest cover that is still sharp at the current terminal size
+            let pane = panes(terminal.lock().await.size()?)[1];
+            let area = widgets::spotify::cover_area(pane);
+            let min_width = (u32::from(area.width) * u32::from(font_size.0)).min(u32::from(area.height) * u32::from(font_size.1));
+            let sizes = covers::sizes(conn, &song.album).await?;
+            // The song is drawn with the placeholder while the cover loads, it
+            // stays when the album has no cover, it cannot be downloaded or it
+            // is not an image
+            if let Some(task) = cover_task.take() {
+                task.abort();
+            }
+            states.lock().await.cover = None;
+            if let Some(cover) = covers::pick(&sizes, min_width) {
+                let (conn, http, url, states, tx) = (conn.clone(), http.clone(), cover.url.clone(), states.clone(), tx.clone());
+                cover_task = Some(tokio::spawn(async move {
+                    let image = covers::get(&conn, &http, &url)
+                        .await
+                        .ok()
+                        .and_then(|data| image::load_from_memory(&data).ok());
+                    if let Some(image) = image {
+                        let mut picker = picker;
+                        states.lock().await.cover = Some(widgets::spotify::Cover::new(&mut picker, image));
+                        // Draws it
+                        tx.send_modify(|_| {});
+                    }
+                }));
+            }
+            let review = decisions::get(conn, &song.song).await?;
+            let annotations = notes::get(conn, &song.song).await?;
+
+            let mut spt_ui = SpotifyUi {
+                title: song.title.to_owned(),
+                artist: song.artist.to_owned(),
+                album_name: song.album_name.to_owned(),
+                album_kind: song.album_kind.to_owned(),
+                release_year: song.release_date.as_deref().and_then(release_year),
+                duration: Duration::from_millis(song.duration as u64),
+                audio_features: *audio_features,
+                rating: review.as_ref().and_then(|review| review.rating),
+                notes: annotations.notes,
+                tags: annotations.tags,
+                popup: None,
+                message: message.take(),
+            };
+            let url = song.preview_url.clone();
+            tx.send(App::Spotify(Box::new(spt_ui.clone())))?;
+
+            let (preview_tx, preview_rx) = tokio::sync::mpsc::unbounded_channel();
+            // Kept until the next song, the preview stops when it is dropped
+            let mut _stream = None;
+            if let Some(url) = url.clone() {
+                match rodio::OutputStream::try_default() {
+                    Ok((stream, stream_handle)) => {
+                        _stream = Some(stream);
+                        tokio::task::spawn(stream_and_play_mp3(url, preview_rx, stream_handle));
+                    }
+                    Err(e) => {
+                        spt_ui.message.get_or_insert_with(|| format!("Pas de sortie audio : {e}"));
+                        tx.send(App::Spotify(Box::new(spt_ui.clone())))?;
+                    }
+                }
+            }
+
+            let action = loop {
+                let event = input_rx.recv().await.ok_or_else(|| anyhow::anyhow!("terminal input closed"))?;
+                let Event::Key(key) = event else { continue };
+                if let Some(popup) = &mut spt_ui.popup {
+                    let mut jump = None;
+                    match key.code {
+                        KeyCode::Char(c) => popup.input.push(c),
+                        KeyCode::Backspace => {
+                            popup.input.pop();
+                        }
+                        KeyCode::Enter => {
+                            match popup.field {
+                                Field::Notes => {
+                                    notes::set_notes(conn, &song.song, &popup.input).await?;
+                                    spt_ui.notes = Some(popup.input.trim().to_owned()).filter(|notes| !notes.is_empty());
+                                }
+                                Field::Tags => {
+                                    let tags = notes::parse_tags(&popup.input);
+                                    notes::set_tags(conn, &song.song, &tags).await?;
+                                    spt_ui.tags = tags;
+                                }
+                                // Keep the filter and go to the first match unless this song is one
+                                Field::Search => {
+                                    let lock = states.lock().await;
+                                    if let Some(matches) = lock.matches.as_ref().filter(|matches| !matches.contains(&index)) {
+                                        jump = next_match(matches, index, false);
+                                    }
+                                }
+                            }
+                            spt_ui.popup = None;
+                        }
+                        KeyCode::Esc => {
+                            if popup.field == Field::Search {
+                                let mut lock = states.lock().await;
+                                lock.matches = None;
+                                lock.select(index);
+                            }
+                            spt_ui.popup = None;
+                        }
+                        _ => {}
+                    }
+                    if let Some(Popup { field: Field::Search, input }) = &spt_ui.popup {
+                        let matches = match search::fts_query(input) {
+                            Some(_) => {
+                                let ids: HashSet<_> = search::search(conn, input).await?.into_iter().collect();
+                                Some((0..spt_songs.len()).filter(|i| ids.contains(&spt_songs[*i].0.id)).collect())
+                            }
+                            None => None,
+                        };
+                        let mut lock = states.lock().await;
+                        lock.matches = matches;
+                        lock.select(index);
+                    }
+                    tx.send(App::Spotify(Box::new(spt_ui.clone())))?;
+                    match jump {
+                        Some(target) if target != index => break Action::Jump(target),
+                        _ => continue,
+                    }
+                }
+                match key.code {
+                    KeyCode::Char('q') => break Action::Quit,
+                    KeyCode::Enter | KeyCode::Char('k') => break Action::Decide(ReviewStatus::Kept),
+                    KeyCode::Char('r') => break Action::Decide(ReviewStatus::Rejected),
+                    KeyCode::Char('l') => break Action::Decide(ReviewStatus::Later),
+                    KeyCode::Char('u') if !undo.is_empty() => break Action::Undo,
+                    KeyCode::Char(digit @ '1'..='5') => {
+                        let rating = digit as u8 - b'0';
+                        undo.push_back(Decision { index, song_id: song.song.clone(), previous: decisions::get(conn, &song.song).await? });
+                        decisions::set_rating(conn, &song.song, rating).await?;
+                        spt_ui.rating = Some(rating.into());
+                        tx.send(App::Spotify(Box::new(spt_ui.clone())))?;
+                    }
+                    KeyCode::Char('a') => {
+                        let input = spt_ui.notes.clone().unwrap_or_default();
+                        spt_ui.popup = Some(Popup { field: Field::Notes, input });
+                        tx.send(App::Spotify(Box::new(spt_ui.clone())))?;
+                    }
+                    KeyCode::Char('t') => {
+                        let input = spt_ui.tags.join(", ");
+                        spt_ui.popup = Some(Popup { field: Field::Tags, input });
+                        tx.send(App::Spotify(Box::new(spt_ui.clone())))?;
+                    }
+                    KeyCode::Char('/') => {
+                        spt_ui.popup = Some(Popup { field: Field::Search, input: String::new() });
+                        let mut lock = states.lock().await;
+                        lock.matches = None;
+                        lock.select(index);
+                        drop(lock);
+                        tx.send(App::Spotify(Box::new(spt_ui.clone())))?;
+                    }
+                    KeyCode::Char(c @ ('n' | 'N')) => {
+                        let target = states.lock().await.matches.as_deref().and_then(|matches| next_match(matches, index, c == 'N'));
+                        match target {
+                            Some(target) if target != index => break Action::Jump(target),
+                            _ => {}
+                        }
+                    }
+                    KeyCode::Esc => {
+                        let mut lock = states.lock().await;
+                        lock.matches = None;
+                        lock.select(index);
+                        drop(lock);
+                        tx.send(App::Spotify(Box::new(spt_ui.clone())))?;
+                    }
+                    KeyCode::Char('y') => {
+                        let search = format!("https://www.youtube.com/results?search_query={}", urlencoding::encode(&format!("{} {}", song.artist, song.title)));
+                        if let Err(e) = open::that(search) {
+                            spt_ui.message = Some(format!("Impossible d'ouvrir le navigateur : {e}"));
+                            tx.send(App::Spotify(Box::new(spt_ui.clone())))?;
+                        }
+                    }
+                    // Nothing plays without an audio output, the receiver is gone then
+                    KeyCode::Char('p') | KeyCode::Char(' ') if url.is_some() => { let _ = preview_tx.send(StreamStatus::Play); }
+                    KeyCode::Left if url.is_some() => { let _ = preview_tx.send(StreamStatus::Skip(-SKIP_SECONDS)); }
+                    KeyCode::Right if url.is_some() => { let _ = preview_tx.send(StreamStatus::Skip(SKIP_SECONDS)); }
+                    code => {
+                        // Move within the songs shown, the search may hide some
+                        let visible = states.lock().await.visible();
+                        let target = match visible.binary_search(&index) {
+                            Ok(position) => navigate(code, position, visible.len()).map(|target| visible[target]),
+                            // From a hidden song, the first step lands on the match next to it
+                            Err(position) => match code {
+                                KeyCode::Down | KeyCode::PageDown => visible.get(position).copied(),
+                                KeyCode::Up | KeyCode::PageUp => position.checked_sub(1).map(|target| visible[target]),
+                                _ => navigate(code, position, visible.len()).map(|target| visible[target]),
+                            },
+                        };
+                        match target {
+                            Some(target) if target != index => break Action::Jump(target),
+                            _ => {}
+                        }
+                    }
+                }
+            };
+
+            match action {
+                Action::Decide(status) => {
+                    undo.push_back(Decision { index, song_id: song.song.clone(), previous: decisions::get(conn, &song.song).await? });
+                    decisions::set_status(conn, &song.song, status).await?;
+                    spt_songs[index].2 = status;
+                    // The next song shown, the last one stays on screen so its decision can still be undone
+                    let next = match states.lock().await.matches.as_deref() {
+                        Some(matches) => next_match(matches, index, false).filter(|next| *next > index),
+                        None => Some(index + 1).filter(|next| *next < spt_songs.len()),
+                    };
+                    match next {
+                        Some(next) => index = next,
+                        None => message = Some("Fin de la liste : U pour annuler, Q pour quitter".to_owned()),
+                    }
+                }
+                Action::Undo => {
+                    let Some(decision) = undo.pop_back() else { continue };
+                    decisions::restore(conn, &decision.song_id, decision.previous.as_ref()).await?;
+                    spt_songs[decision.index].2 = decision.previous.map(|review| review.status).unwrap_or_default();
+                    index = decision.index;
+                }
+                Action::Jump(target) => index = target,
+                Action::Quit => break,
+            }
+            while undo.len() > UNDO_DEPTH {
+                undo.pop_front();
+            }
+            let mut lock = states.lock().await;
+            for (item, (song, _, status)) in lock.spt_list.items.iter_mut().zip(&spt_songs) {
+                item.1 = status_color(*status, song.removed);
+            }
+            lock.select(index.min(spt_songs.len().saturating_sub(1)));
+        }
+        Ok(())
+    }
+    .await;
+
+    // Nothing draws or reads the terminal once it is restored
+    watcher.abort();
+    ui_abort.abort();
+    input_abort.abort();
+    let mut terminal = terminal.lock().await;
+    restore_terminal(&mut terminal).map_err(|e| anyhow::anyhow!("{e}"))?;
+    result
+}
@@ -0,0 +1,60 @@
+use sqlx::SqlitePool;
+
+/// FTS5 query matching the songs where every word of `input` starts a word of
+/// the title, artists, album or tags, in any order and ignoring accents.
+/// `None` when there is nothing to search.
+pub fn fts_query(input: &str) -> Option<String> {
+    let words: Vec<_> = input
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{word}\"*"))
+        .collect();
+    (!words.is_empty()).then(|| words.join(" "))
+}
+
+/// IDs of the songs matching `input`, best matches first.
+pub async fn search(conn: &SqlitePool, input: &str) -> sqlx::Result<Vec<String>> {
+    let Some(query) = fts_query(input) else {
+        return Ok(Vec::new());
+    };
+    sqlx::query_scalar!(
+        "SELECT spt_song_id AS \"spt_song_id!: String\" FROM spt_songs_search WHERE spt_songs_search MATCH $1 ORDER BY rank",
+        query
+    )
+    .fetch_all(conn)
+    .await
+}
+
+/// Rebuilds the whole index, after a sync.
+pub async fn reindex(conn: &SqlitePool) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+    sqlx::query!("DELETE FROM spt_songs_search").execute(&mut *tx).await?;
+    sqlx::query!(
+        "INSERT INTO spt_songs_search(spt_song_id, title, artist, album, tags)
+        SELECT spt_songs.id, spt_songs.title,
+            coalesce((SELECT group_concat(spt_artists.name, ' ')
+                FROM spt_songs_spt_artists
+                INNER JOIN spt_artists ON spt_artists.id = spt_songs_spt_artists.spt_artist_id
+                WHERE spt_songs_spt_artists.spt_song_id = spt_songs.id), spt_songs.artist),
+            spt_albums.name,
+            (SELECT group_concat(tag, ' ') FROM songs_tags WHERE song_id = spt_songs.song)
+        FROM spt_songs
+        INNER JOIN spt_albums ON spt_albums.id = spt_songs.album"
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await
+}
+
+/// Updates the tags of a song, `songs.id`, in the index.
+pub async fn reindex_tags(conn: &SqlitePool, song_id: &str) -> sqlx::Result<()> {
+    sqlx::query!(
+        "UPDATE spt_songs_search
+        SET tags = (SELECT group_concat(tag, ' ') FROM songs_tags WHERE song_id = $1)
+        WHERE spt_song_id IN (SELECT id FROM spt_songs WHERE song = $1)",
+        song_id
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use sqlx::SqlitePool;
+
+/// A review queue that resumes where it was left: its shuffle seed and the
+/// song, `spt_songs.id`, reviewed last.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    pub name: String,
+    pub seed: i64,
+    pub current_spt_song_id: Option<String>,
+}
+
+/// The session with this name, started with a new seed if it does not exist
+/// yet or `reshuffle` is set.
+pub async fn open(conn: &SqlitePool, name: &str, reshuffle: bool) -> anyhow::Result<Session> {
+    let existing = sqlx::query!(
+        "SELECT seed, current_spt_song_id FROM review_sessions WHERE name = $1",
+        name
+    )
+    .fetch_optional(conn)
+    .await?;
+    if let (Some(session), false) = (existing, reshuffle) {
+        return Ok(Session {
+            name: name.to_owned(),
+            seed: session.seed,
+            current_spt_song_id: session.current_spt_song_id,
+        });
+    }
+
+    let seed: i64 = rand::random();
+    let updated_at = chrono::Utc::now().to_rfc3339();
+    sqlx::query!(
+        "INSERT OR REPLACE INTO review_sessions(name, seed, current_spt_song_id, updated_at) VALUES ($1, $2, NULL, $3)",
+        name,
+        seed,
+        updated_at
+    )
+    .execute(conn)
+    .await?;
+    Ok(Session {
+        name: name.to_owned(),
+        seed,
+        current_spt_song_id: None,
+    })
+}
+
+/// Remembers the song on screen, the next launch starts from it.
+pub async fn save_position(conn: &SqlitePool, name: &str, spt_song_id: &str) -> sqlx::Result<()> {
+    let updated_at = chrono::Utc::now().to_rfc3339();
+    sqlx::query!(
+        "UPDATE review_sessions SET current_spt_song_id = $1, updated_at = $2 WHERE name = $3",
+        spt_song_id,
+        updated_at,
+        name
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Sort key of a song in the shuffle of `seed`. Every song keeps its key when
+/// a sync adds or removes others, so the rest of the queue does not move.
+pub fn shuffle_key(seed: i64, spt_song_id: &str) -> u64 {
+    // FNV-1a, then the splitmix64 finalizer to spread close IDs apart
+    let mut hash = 0xcbf29ce484222325_u64;
+    for byte in seed.to_le_bytes().iter().chain(spt_song_id.as_bytes()) {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+    hash ^ (hash >> 31)
+}
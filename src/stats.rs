@@ -0,0 +1,59 @@
+use sqlx::SqlitePool;
+
+use crate::review::DisplayTimestamp;
+
+pub async fn print_stats(conn: &SqlitePool) -> anyhow::Result<()> {
+    let songs = sqlx::query!(
+        "SELECT count(*) AS count, count(preview_url) AS previews, coalesce(sum(duration), 0) AS \"duration: i64\"
+        FROM spt_songs"
+    )
+    .fetch_one(conn)
+    .await?;
+    let albums = sqlx::query_scalar!("SELECT count(*) FROM spt_albums")
+        .fetch_one(conn)
+        .await?;
+    let artists = sqlx::query_scalar!("SELECT count(*) FROM spt_artists")
+        .fetch_one(conn)
+        .await?;
+
+    let reviews = sqlx::query!(
+        "SELECT coalesce(song_reviews.status, 'pending') AS \"status!: String\", count(*) AS \"songs!: i64\"
+        FROM spt_songs
+        LEFT JOIN song_reviews ON song_reviews.song_id = spt_songs.song
+        GROUP BY 1
+        ORDER BY 1"
+    )
+    .fetch_all(conn)
+    .await?;
+    let genres = sqlx::query!(
+        "SELECT spt_genre AS genre, count(DISTINCT spt_songs_spt_artists.spt_song_id) AS \"songs!: i64\"
+        FROM spt_artists_spt_genres
+        INNER JOIN spt_songs_spt_artists ON spt_songs_spt_artists.spt_artist_id = spt_artists_spt_genres.spt_artist_id
+        GROUP BY spt_genre
+        ORDER BY 2 DESC, spt_genre
+        LIMIT 10"
+    )
+    .fetch_all(conn)
+    .await?;
+
+    let duration = chrono::Duration::milliseconds(songs.duration);
+    println!("Songs:    {} ({} with a preview)", songs.count, songs.previews);
+    println!("Albums:   {albums}");
+    println!("Artists:  {artists}");
+    println!(
+        "Duration: {}h {}",
+        duration.num_hours(),
+        (duration - chrono::Duration::hours(duration.num_hours())).display_timestamp()?
+    );
+    if !reviews.is_empty() {
+        let reviews: Vec<_> = reviews.iter().map(|review| format!("{} {}", review.songs, review.status)).collect();
+        println!("Reviews:  {}", reviews.join(", "));
+    }
+    if !genres.is_empty() {
+        println!("Top genres:");
+        for genre in genres {
+            println!("  {:>6} songs  {}", genre.songs, genre.genre);
+        }
+    }
+    Ok(())
+}
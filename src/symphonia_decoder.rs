@@ -4,11 +4,11 @@ use symphonia::{
         audio::{AudioBufferRef, SampleBuffer, SignalSpec},
         codecs::{Decoder, DecoderOptions},
         errors::Error,
-        formats::{FormatOptions, FormatReader},
+        formats::{FormatOptions, FormatReader, SeekMode, SeekTo},
         io::MediaSourceStream,
         meta::MetadataOptions,
         probe::Hint,
-        units,
+        units::{self, Time, TimeBase, TimeStamp},
     },
     default::get_probe,
 };
@@ -28,6 +28,11 @@ pub struct SymphoniaDecoder {
     format: Box<dyn FormatReader>,
     buffer: SampleBuffer<i16>,
     spec: SignalSpec,
+    track_id: u32,
+    time_base: Option<TimeBase>,
+    total_duration: Option<Duration>,
+    /// Timestamp of the first frame in `buffer`
+    ts: TimeStamp,
 }
 
 impl SymphoniaDecoder {
@@ -48,10 +53,6 @@ impl SymphoniaDecoder {
         }
     }
 
-    pub fn into_inner(self: Box<Self>) -> MediaSourceStream {
-        self.format.into_inner()
-    }
-
     fn init(
         mss: MediaSourceStream,
         extension: Option<&str>,
@@ -74,17 +75,21 @@ impl SymphoniaDecoder {
 
         let mut decoder = symphonia::default::get_codecs().make(
             &stream.codec_params,
-            &DecoderOptions {
-                verify: true,
-                ..Default::default()
-            },
+            &DecoderOptions { verify: true },
         )?;
+        let track_id = stream.id;
+        let time_base = stream.codec_params.time_base;
+        let total_duration = match (stream.codec_params.n_frames, time_base, stream.codec_params.sample_rate) {
+            (Some(n_frames), Some(time_base), _) => Some(duration(time_base.calc_time(n_frames))),
+            (Some(n_frames), None, Some(rate)) => Some(Duration::from_secs_f64(n_frames as f64 / f64::from(rate))),
+            _ => None,
+        };
 
         let mut decode_errors: usize = 0;
-        let decoded = loop {
+        let (decoded, ts) = loop {
             let current_frame = probed.format.next_packet()?;
             match decoder.decode(&current_frame) {
-                Ok(decoded) => break decoded,
+                Ok(decoded) => break (decoded, current_frame.ts),
                 Err(e) => match e {
                     Error::DecodeError(_) => {
                         decode_errors += 1;
@@ -101,28 +106,86 @@ impl SymphoniaDecoder {
         let spec = decoded.spec().to_owned();
         let buffer = SymphoniaDecoder::get_buffer(decoded, &spec);
 
-        return Ok(Some(SymphoniaDecoder {
+        Ok(Some(SymphoniaDecoder {
             decoder,
             current_frame_offset: 0,
             format: probed.format,
             buffer,
             spec,
-        }));
+            track_id,
+            time_base,
+            total_duration,
+            ts,
+        }))
+    }
+
+    /// Time from the start of the song to the next sample.
+    pub fn elapsed(&self) -> Duration {
+        let frames = self.current_frame_offset / self.spec.channels.count();
+        self.time(self.ts) + Duration::from_secs_f64(frames as f64 / f64::from(self.spec.rate))
+    }
+
+    /// Moves to `pos` from the start of the song. The format reader seeks to
+    /// the packet before it, the decoder starts over from there and the
+    /// samples before `pos` are skipped. The song ends when `pos` is past it.
+    pub fn seek(&mut self, pos: Duration) -> symphonia::core::errors::Result<()> {
+        let time = Time::new(pos.as_secs(), f64::from(pos.subsec_nanos()) / 1e9);
+        let seeked = self.format.seek(
+            SeekMode::Accurate,
+            SeekTo::Time {
+                time,
+                track_id: Some(self.track_id),
+            },
+        )?;
+        // Decoders keep state from the packets before
+        self.decoder.reset();
+        self.buffer.clear();
+        self.current_frame_offset = 0;
+
+        let mut decode_errors: usize = 0;
+        loop {
+            let packet = self.format.next_packet()?;
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                Err(Error::DecodeError(_)) if decode_errors < MAX_DECODE_ERRORS => {
+                    decode_errors += 1;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            // Decoded anyway, MP3 frames depend on the previous ones
+            if packet.ts + decoded.frames() as u64 <= seeked.required_ts {
+                continue;
+            }
+            self.spec = decoded.spec().to_owned();
+            self.buffer = SymphoniaDecoder::get_buffer(decoded, &self.spec);
+            self.ts = packet.ts;
+            let skipped = seeked.required_ts.saturating_sub(packet.ts) as usize * self.spec.channels.count();
+            self.current_frame_offset = skipped.min(self.buffer.len());
+            return Ok(());
+        }
+    }
+
+    fn time(&self, ts: TimeStamp) -> Duration {
+        match self.time_base {
+            Some(time_base) => duration(time_base.calc_time(ts)),
+            None => Duration::from_secs_f64(ts as f64 / f64::from(self.spec.rate)),
+        }
     }
 
     #[inline]
     fn get_buffer(decoded: AudioBufferRef, spec: &SignalSpec) -> SampleBuffer<i16> {
         let duration = units::Duration::from(decoded.capacity() as u64);
-        let mut buffer = SampleBuffer::<i16>::new(duration, spec.clone());
+        let mut buffer = SampleBuffer::<i16>::new(duration, *spec);
         buffer.copy_interleaved_ref(decoded);
-        return buffer;
+        buffer
     }
 }
 
 impl Source for SymphoniaDecoder {
     #[inline]
     fn current_frame_len(&self) -> Option<usize> {
-        Some(self.buffer.samples().len())
+        Some(self.buffer.len() - self.current_frame_offset)
     }
 
     #[inline]
@@ -137,7 +200,7 @@ impl Source for SymphoniaDecoder {
 
     #[inline]
     fn total_duration(&self) -> Option<Duration> {
-        None
+        self.total_duration
     }
 }
 
@@ -148,10 +211,10 @@ impl Iterator for SymphoniaDecoder {
     fn next(&mut self) -> Option<i16> {
         if self.current_frame_offset == self.buffer.len() {
             let mut decode_errors: usize = 0;
-            let decoded = loop {
+            let (decoded, ts) = loop {
                 match self.format.next_packet() {
                     Ok(packet) => match self.decoder.decode(&packet) {
-                        Ok(decoded) => break decoded,
+                        Ok(decoded) => break (decoded, packet.ts),
                         Err(e) => match e {
                             Error::DecodeError(_) => {
                                 decode_errors += 1;
@@ -170,6 +233,7 @@ impl Iterator for SymphoniaDecoder {
             self.spec = decoded.spec().to_owned();
             self.buffer = SymphoniaDecoder::get_buffer(decoded, &self.spec);
             self.current_frame_offset = 0;
+            self.ts = ts;
         }
 
         let sample = self.buffer.samples()[self.current_frame_offset];
@@ -177,4 +241,8 @@ impl Iterator for SymphoniaDecoder {
 
         Some(sample)
     }
-}
\ No newline at end of file
+}
+
+fn duration(time: Time) -> Duration {
+    Duration::from_secs(time.seconds) + Duration::from_secs_f64(time.frac)
+}
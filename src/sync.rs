@@ -0,0 +1,1007 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
+
+use anyhow::anyhow;
+use base64::Engine;
+use futures::stream::StreamExt;
+use rspotify::{
+    model::{AlbumId, ArtistId, FullTrack, Market, PlayableItem, PlaylistId, PlaylistItem, TrackId},
+    http::HttpError,
+    prelude::*,
+    AuthCodePkceSpotify, ClientCredsSpotify, ClientError, Config, Credentials,
+};
+
+use crate::{covers, playlists::Source, retry::RetryPolicy, search};
+
+/// Number of playlist items requested per page, the Web API maximum.
+const PAGE_SIZE: u32 = 100;
+/// The library and several-tracks endpoints stop at 50 items per request.
+const LIBRARY_PAGE_SIZE: u32 = 50;
+/// The several-albums endpoint stops at 20 albums per request.
+const ALBUMS_BATCH_SIZE: usize = 20;
+/// The several-artists endpoint stops at 50 artists per request.
+const ARTISTS_BATCH_SIZE: usize = 50;
+/// The several-audio-features endpoint stops at 100 tracks per request.
+const AUDIO_FEATURES_BATCH_SIZE: usize = 100;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SyncOptions {
+    /// Walk every playlist even if its snapshot ID did not change
+    pub full: bool,
+    /// Delete the songs that left every playlist once the sync is done
+    pub prune: bool,
+    /// How many tracks are written to the database at the same time
+    pub concurrency: usize,
+    /// Also import tempo, key, energy and the other audio features
+    pub audio_features: bool,
+    /// Also download the album covers missing from the cache
+    pub covers: bool,
+    pub retry: RetryPolicy,
+}
+
+/// Authenticates with the app credentials found in the environment (or `.env`).
+pub async fn client_credentials(config: Config, retry: &RetryPolicy) -> anyhow::Result<ClientCredsSpotify> {
+    let creds = Credentials::from_env()
+        .ok_or_else(|| anyhow!("RSPOTIFY_CLIENT_ID and RSPOTIFY_CLIENT_SECRET must be set"))?;
+    let spotify = ClientCredsSpotify::with_config(creds, config);
+    retry.run(|| spotify.request_token()).await?;
+    Ok(spotify)
+}
+
+/// Pulls every registered playlist and library source. Playlists whose
+/// snapshot ID did not change since the last run are skipped unless
+/// `options.full` is set. An interrupted sync resumes from the last page it
+/// completed as long as the playlist snapshot is the same. Library sources
+/// have no snapshot and are walked in full, with the `user` client. Failed
+/// playlists and tracks do not stop the album, artist and search updates of
+/// the songs that did sync, only `options.prune`.
+pub async fn sync_from_spotify(
+    conn: &sqlx::SqlitePool,
+    spotify: &impl BaseClient,
+    user: Option<&AuthCodePkceSpotify>,
+    options: SyncOptions,
+) -> anyhow::Result<()> {
+    let playlists = sqlx::query!("SELECT id, snapshot_id FROM spt_playlists")
+        .fetch_all(conn)
+        .await?;
+    if playlists.is_empty() {
+        return Err(anyhow!("no playlist to sync, register one with `exospot playlist add`"));
+    }
+
+    let summary = Mutex::new(SyncSummary::default());
+    for playlist in playlists {
+        let snapshot_id = if options.full { None } else { playlist.snapshot_id };
+        let result = match Source::library(&playlist.id) {
+            Some(source) => match user {
+                Some(user) => sync_library(conn, user, &options, &source, &summary).await,
+                None => Err(anyhow!("run `exospot login` to sync the user library")),
+            },
+            None => sync_playlist(conn, spotify, &options, &playlist.id, snapshot_id.as_deref(), &summary).await,
+        };
+        // Keep going with the other playlists, this one resumes on the next run
+        if let Err(error) = result {
+            summary.lock().unwrap().failed_playlists.push((playlist.id, error));
+        }
+    }
+    let summary = summary.into_inner().unwrap();
+    summary.print();
+    let mut errors = Vec::new();
+    if !summary.failed_playlists.is_empty() {
+        errors.push(anyhow!("{} playlists failed to sync", summary.failed_playlists.len()));
+    }
+    if !summary.failed.is_empty() {
+        errors.push(anyhow!("{} tracks failed to sync", summary.failed.len()));
+    }
+    let synced = errors.is_empty();
+
+    // The songs that made it are enriched and indexed even if others failed
+    if let Err(error) = sync_album_details(conn, spotify, &options).await {
+        errors.push(error.context("could not sync the album details"));
+    }
+    if let Err(error) = sync_artist_details(conn, spotify, &options).await {
+        errors.push(error.context("could not sync the artist details"));
+    }
+    if options.audio_features {
+        if let Err(error) = sync_audio_features(conn, spotify, &options).await {
+            errors.push(error.context("could not sync the audio features"));
+        }
+    }
+    // A song of a failed playlist or track would look removed
+    if options.prune {
+        if !synced {
+            println!("Not pruning, the sync is incomplete");
+        } else if let Err(error) = prune_orphans(conn).await {
+            errors.push(error.context("could not prune the removed songs"));
+        }
+    }
+    if options.covers {
+        if let Err(error) = covers::cache_missing(conn, options.concurrency).await {
+            errors.push(error.context("could not cache the covers"));
+        }
+    }
+    search::reindex(conn).await?;
+
+    if errors.is_empty() {
+        return Ok(())
+    }
+    let errors: Vec<_> = errors.iter().map(|error| format!("{error:#}")).collect();
+    Err(anyhow!("{}", errors.join(", ")))
+}
+
+/// Track objects only carry a simplified album, the label, UPC and track count
+/// come from the full album. Albums synced before these columns existed are
+/// caught up the same way, and every album with `options.full`, which also
+/// stores the cover sizes older databases missed.
+async fn sync_album_details(
+    conn: &sqlx::SqlitePool,
+    spotify: &impl BaseClient,
+    options: &SyncOptions,
+) -> anyhow::Result<()> {
+    let album_ids = sqlx::query_scalar!("SELECT id FROM spt_albums WHERE total_tracks IS NULL OR $1", options.full)
+        .fetch_all(conn)
+        .await?
+        .iter()
+        .map(|id| AlbumId::from_id_or_uri(id).map(AlbumId::into_static))
+        .collect::<Result<Vec<_>, _>>()?;
+    for chunk in album_ids.chunks(ALBUMS_BATCH_SIZE) {
+        let albums = options
+            .retry
+            .run(|| spotify.albums(chunk.iter().map(|id| id.as_ref())))
+            .await?;
+        let mut tx = conn.begin().await?;
+        for album in albums {
+            let album_id = album.id.to_string();
+            let precision: &str = album.release_date_precision.into();
+            let upc = album.external_ids.get("upc");
+            let updated = sqlx::query!(
+                "UPDATE spt_albums SET release_date = $1, release_date_precision = $2, label = $3, upc = $4, total_tracks = $5 WHERE id = $6",
+                album.release_date,
+                precision,
+                album.label,
+                upc,
+                album.tracks.total,
+                album_id
+            )
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            // Pruned since the IDs were read
+            if updated == 0 {
+                continue
+            }
+            if !album.images.is_empty() {
+                sqlx::query!("DELETE FROM spt_albums_covers WHERE album_id = $1", album_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            for image in &album.images {
+                sqlx::query!(
+                    "INSERT OR IGNORE INTO spt_albums_covers(album_id, url, height, width) VALUES ($1, $2, $3, $4)",
+                    album_id,
+                    image.url,
+                    image.height,
+                    image.width
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+        tx.commit().await?;
+    }
+    Ok(())
+}
+
+/// Track objects only name their artists. Genres, popularity, followers and
+/// images are fetched for the artists that never had them, or for every artist
+/// with `options.full` since they change over time.
+async fn sync_artist_details(
+    conn: &sqlx::SqlitePool,
+    spotify: &impl BaseClient,
+    options: &SyncOptions,
+) -> anyhow::Result<()> {
+    let artist_ids = sqlx::query_scalar!("SELECT id FROM spt_artists WHERE followers IS NULL OR $1", options.full)
+        .fetch_all(conn)
+        .await?
+        .iter()
+        .map(|id| ArtistId::from_id_or_uri(id).map(ArtistId::into_static))
+        .collect::<Result<Vec<_>, _>>()?;
+    for chunk in artist_ids.chunks(ARTISTS_BATCH_SIZE) {
+        let artists = options
+            .retry
+            .run(|| spotify.artists(chunk.iter().map(|id| id.as_ref())))
+            .await?;
+        let mut tx = conn.begin().await?;
+        for artist in artists {
+            let artist_id = artist.id.to_string();
+            let updated = sqlx::query!(
+                "UPDATE spt_artists SET popularity = $1, followers = $2 WHERE id = $3",
+                artist.popularity,
+                artist.followers.total,
+                artist_id
+            )
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            // Pruned since the IDs were read
+            if updated == 0 {
+                continue
+            }
+            sqlx::query!("DELETE FROM spt_artists_spt_genres WHERE spt_artist_id = $1", artist_id)
+                .execute(&mut *tx)
+                .await?;
+            for genre in &artist.genres {
+                sqlx::query!("INSERT OR IGNORE INTO spt_genres(name) VALUES ($1)", genre)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query!(
+                    "INSERT OR IGNORE INTO spt_artists_spt_genres(spt_artist_id, spt_genre) VALUES ($1, $2)",
+                    artist_id,
+                    genre
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+            sqlx::query!("DELETE FROM spt_artists_images WHERE artist_id = $1", artist_id)
+                .execute(&mut *tx)
+                .await?;
+            for image in &artist.images {
+                sqlx::query!(
+                    "INSERT OR IGNORE INTO spt_artists_images(artist_id, url, height, width) VALUES ($1, $2, $3, $4)",
+                    artist_id,
+                    image.url,
+                    image.height,
+                    image.width
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+        tx.commit().await?;
+    }
+    Ok(())
+}
+
+/// Imports the audio features of the songs that have none yet. Spotify has no
+/// features for some tracks, these are asked for again on the next run.
+async fn sync_audio_features(
+    conn: &sqlx::SqlitePool,
+    spotify: &impl BaseClient,
+    options: &SyncOptions,
+) -> anyhow::Result<()> {
+    let track_ids = sqlx::query_scalar!(
+        "SELECT id FROM spt_songs WHERE NOT EXISTS (SELECT 1 FROM spt_audio_features WHERE spt_song_id = spt_songs.id)"
+    )
+    .fetch_all(conn)
+    .await?
+    .iter()
+    .map(|id| TrackId::from_id(id).map(TrackId::into_static))
+    .collect::<Result<Vec<_>, _>>()?;
+    let mut imported = 0;
+    for chunk in track_ids.chunks(AUDIO_FEATURES_BATCH_SIZE) {
+        let features = match options.retry.run(|| spotify.tracks_features(chunk.iter().map(|id| id.as_ref()))).await {
+            Ok(features) => features.unwrap_or_default(),
+            // A track without features is a null in the batch, which rspotify cannot parse
+            Err(ClientError::ParseJson(_)) => {
+                let mut features = Vec::new();
+                for id in chunk {
+                    match options.retry.run(|| spotify.track_features(id.as_ref())).await {
+                        Ok(track_features) => features.push(track_features),
+                        Err(ClientError::ParseJson(_)) => {}
+                        Err(ClientError::Http(error)) if is_not_found(&error) => {}
+                        Err(error) => return Err(error.into()),
+                    }
+                }
+                features
+            }
+            Err(error) => return Err(error.into()),
+        };
+        let mut tx = conn.begin().await?;
+        for features in &features {
+            let song_id = features.id.id();
+            let mode = features.mode as i64;
+            imported += sqlx::query!(
+                "INSERT OR REPLACE INTO spt_audio_features(spt_song_id, tempo, key, mode, time_signature, energy, valence, danceability, loudness)
+                SELECT $1, $2, $3, $4, $5, $6, $7, $8, $9 WHERE EXISTS (SELECT 1 FROM spt_songs WHERE id = $1)",
+                song_id,
+                features.tempo,
+                features.key,
+                mode,
+                features.time_signature,
+                features.energy,
+                features.valence,
+                features.danceability,
+                features.loudness
+            )
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        }
+        tx.commit().await?;
+    }
+    println!("Imported the audio features of {imported} songs, {} have none", (track_ids.len() as u64).saturating_sub(imported));
+    Ok(())
+}
+
+fn is_not_found(error: &HttpError) -> bool {
+    matches!(error, HttpError::StatusCode(response) if response.status() == reqwest::StatusCode::NOT_FOUND)
+}
+
+/// Deletes the songs that are not in any registered playlist anymore, along
+/// with the albums, covers, artists and genres only they were referencing.
+async fn prune_orphans(conn: &sqlx::SqlitePool) -> anyhow::Result<()> {
+    let mut tx = conn.begin().await?;
+    sqlx::query!(
+        "DELETE FROM spt_songs_spt_artists WHERE spt_song_id IN (
+            SELECT id FROM spt_songs WHERE NOT EXISTS (
+                SELECT 1 FROM spt_songs_spt_playlists WHERE spt_song_id = spt_songs.id AND removed_at IS NULL
+            )
+        )"
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        "DELETE FROM spt_audio_features WHERE spt_song_id IN (
+            SELECT id FROM spt_songs WHERE NOT EXISTS (
+                SELECT 1 FROM spt_songs_spt_playlists WHERE spt_song_id = spt_songs.id AND removed_at IS NULL
+            )
+        )"
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        "DELETE FROM spt_songs_spt_playlists WHERE spt_song_id IN (
+            SELECT id FROM spt_songs WHERE NOT EXISTS (
+                SELECT 1 FROM spt_songs_spt_playlists WHERE spt_song_id = spt_songs.id AND removed_at IS NULL
+            )
+        )"
+    )
+    .execute(&mut *tx)
+    .await?;
+    let songs = sqlx::query!(
+        "DELETE FROM spt_songs WHERE NOT EXISTS (
+            SELECT 1 FROM spt_songs_spt_playlists WHERE spt_song_id = spt_songs.id AND removed_at IS NULL
+        )"
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    sqlx::query!("DELETE FROM song_reviews WHERE song_id NOT IN (SELECT song FROM spt_songs)")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM songs_tags WHERE song_id NOT IN (SELECT song FROM spt_songs)")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM songs WHERE id NOT IN (SELECT song FROM spt_songs)")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM tags WHERE name NOT IN (SELECT tag FROM songs_tags)")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM spt_albums_covers WHERE album_id NOT IN (SELECT album FROM spt_songs)")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM spt_covers_data WHERE url NOT IN (SELECT url FROM spt_albums_covers)")
+        .execute(&mut *tx)
+        .await?;
+    let albums = sqlx::query!("DELETE FROM spt_albums WHERE id NOT IN (SELECT album FROM spt_songs)")
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    sqlx::query!(
+        "DELETE FROM spt_artists_spt_genres WHERE spt_artist_id NOT IN (SELECT spt_artist_id FROM spt_songs_spt_artists)"
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!("DELETE FROM spt_artists_images WHERE artist_id NOT IN (SELECT spt_artist_id FROM spt_songs_spt_artists)")
+        .execute(&mut *tx)
+        .await?;
+    let artists = sqlx::query!(
+        "DELETE FROM spt_artists WHERE id NOT IN (SELECT spt_artist_id FROM spt_songs_spt_artists)"
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    sqlx::query!("DELETE FROM spt_genres WHERE name NOT IN (SELECT spt_genre FROM spt_artists_spt_genres)")
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    println!("Pruned {songs} songs, {albums} albums and {artists} artists");
+    Ok(())
+}
+
+/// Why a single track could not be written to the database.
+#[derive(Debug, thiserror::Error)]
+pub enum TrackError {
+    #[error("track has no artist")]
+    NoArtist,
+    #[error("album has no ID")]
+    NoAlbumId,
+    #[error("album has no type")]
+    NoAlbumType,
+    #[error("artist {0} has no ID")]
+    NoArtistId(String),
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+}
+
+#[derive(Debug)]
+pub struct FailedTrack {
+    pub playlist_id: String,
+    pub title: String,
+    pub error: TrackError,
+}
+
+#[derive(Debug, Default)]
+pub struct SyncSummary {
+    pub added: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub removed: usize,
+    pub failed: Vec<FailedTrack>,
+    pub failed_playlists: Vec<(String, anyhow::Error)>,
+}
+
+impl SyncSummary {
+    fn print(&self) {
+        println!(
+            "Added {}, updated {}, skipped {}, removed {}, failed {}",
+            self.added,
+            self.updated,
+            self.skipped,
+            self.removed,
+            self.failed.len()
+        );
+        for failed in &self.failed {
+            println!("  {} ({}): {}", failed.title, failed.playlist_id, failed.error);
+        }
+        for (playlist_id, error) in &self.failed_playlists {
+            println!("  Playlist {playlist_id}: {error:#}");
+        }
+    }
+}
+
+enum TrackOutcome {
+    Added,
+    Updated,
+    Skipped,
+}
+
+async fn sync_playlist(
+    conn: &sqlx::SqlitePool,
+    spotify: &impl BaseClient,
+    options: &SyncOptions,
+    playlist_id: &str,
+    last_snapshot_id: Option<&str>,
+    summary: &Mutex<SyncSummary>,
+) -> anyhow::Result<()> {
+    let id = PlaylistId::from_id(playlist_id)?;
+    let full_playlist = options
+        .retry
+        .run(|| spotify.playlist(id.as_ref(), None, None))
+        .await?;
+    sqlx::query!("UPDATE spt_playlists SET name = $1 WHERE id = $2", full_playlist.name, playlist_id)
+        .execute(conn)
+        .await?;
+    if last_snapshot_id == Some(full_playlist.snapshot_id.as_str()) && !has_incomplete_songs(conn, playlist_id).await? {
+        println!("Playlist {} ({playlist_id}) is unchanged", full_playlist.name);
+        return Ok(())
+    }
+
+    // Resume an interrupted sync of the same snapshot, start over otherwise
+    let progress = sqlx::query!(
+        "SELECT next_offset FROM spt_sync_progress WHERE spt_playlist_id = $1 AND snapshot_id = $2",
+        playlist_id,
+        full_playlist.snapshot_id
+    )
+    .fetch_optional(conn)
+    .await?;
+    let mut offset = match progress {
+        Some(progress) => {
+            println!("Resuming playlist {} ({playlist_id}) at track {}", full_playlist.name, progress.next_offset);
+            progress.next_offset as u32
+        }
+        None => {
+            println!("Syncing playlist {} ({playlist_id})", full_playlist.name);
+            let mut tx = conn.begin().await?;
+            clear_progress(&mut tx, playlist_id).await?;
+            sqlx::query!(
+                "INSERT INTO spt_sync_progress(spt_playlist_id, snapshot_id, next_offset) VALUES ($1, $2, 0)",
+                playlist_id,
+                full_playlist.snapshot_id
+            )
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+            0
+        }
+    };
+
+    let known_songs = load_known_songs(conn).await?;
+    let memberships = load_memberships(conn, playlist_id).await?;
+    let seen: HashSet<String> = sqlx::query_scalar!(
+        "SELECT spt_song_id FROM spt_sync_progress_songs WHERE spt_playlist_id = $1",
+        playlist_id
+    )
+    .fetch_all(conn)
+    .await?
+    .into_iter()
+    .collect();
+    let seen = Mutex::new(seen);
+    let failed_before = summary.lock().unwrap().failed.len();
+
+    loop {
+        let page = options
+            .retry
+            .run(|| spotify.playlist_items_manual(id.as_ref(), None, None, Some(PAGE_SIZE), Some(offset)))
+            .await?;
+        let page_songs: Vec<String> = page
+            .items
+            .iter()
+            .filter_map(|item| match &item.track {
+                Some(PlayableItem::Track(track)) => track.id.as_ref().map(|id| id.id().to_owned()),
+                _ => None,
+            })
+            .collect();
+        let page_len = page.items.len() as u32;
+
+        futures::stream::iter(page.items.into_iter().enumerate())
+            .for_each_concurrent(options.concurrency, |(index, item)| {
+                let position = (offset as usize + index) as i64;
+                sync_item(conn, playlist_id, position, item, &known_songs, &memberships, &seen, summary)
+            })
+            .await;
+
+        offset += page_len;
+        // Progress only covers the pages where every track made it
+        if summary.lock().unwrap().failed.len() == failed_before {
+            let mut tx = conn.begin().await?;
+            for song_id in &page_songs {
+                sqlx::query!(
+                    "INSERT OR IGNORE INTO spt_sync_progress_songs(spt_playlist_id, spt_song_id) VALUES ($1, $2)",
+                    playlist_id,
+                    song_id
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+            sqlx::query!("UPDATE spt_sync_progress SET next_offset = $1 WHERE spt_playlist_id = $2", offset, playlist_id)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+        }
+        if page.next.is_none() {
+            break
+        }
+    }
+
+    // A failed track would look removed and the snapshot must be pulled again
+    if summary.lock().unwrap().failed.len() > failed_before {
+        return Ok(())
+    }
+
+    let synced_at = chrono::Utc::now().to_rfc3339();
+    let mut tx = conn.begin().await?;
+
+    let removed = mark_removed(&mut tx, playlist_id, &memberships, &seen.into_inner().unwrap(), &synced_at).await?;
+    sqlx::query!(
+        "UPDATE spt_playlists SET snapshot_id = $1, synced_at = $2 WHERE id = $3",
+        full_playlist.snapshot_id,
+        synced_at,
+        playlist_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    clear_progress(&mut tx, playlist_id).await?;
+    tx.commit().await?;
+    summary.lock().unwrap().removed += removed;
+    Ok(())
+}
+
+/// Library sources have no snapshot ID, so all their tracks are fetched before
+/// being written like the items of a playlist.
+async fn sync_library(
+    conn: &sqlx::SqlitePool,
+    spotify: &AuthCodePkceSpotify,
+    options: &SyncOptions,
+    source: &Source,
+    summary: &Mutex<SyncSummary>,
+) -> anyhow::Result<()> {
+    let source_id = source.id();
+    let name = source.library_name().unwrap_or(source_id);
+    println!("Syncing {name}");
+    let items = match source {
+        Source::LikedSongs => fetch_liked_songs(spotify, options).await?,
+        Source::SavedAlbums => fetch_saved_albums(spotify, options).await?,
+        Source::FollowedArtists => fetch_followed_artists(spotify, options).await?,
+        Source::Playlist(_) => unreachable!("playlists are synced by sync_playlist"),
+    };
+
+    let known_songs = load_known_songs(conn).await?;
+    let memberships = load_memberships(conn, source_id).await?;
+    let seen = Mutex::new(HashSet::new());
+    let failed_before = summary.lock().unwrap().failed.len();
+    futures::stream::iter(items.into_iter().enumerate())
+        .for_each_concurrent(options.concurrency, |(position, item)| {
+            sync_item(conn, source_id, position as i64, item, &known_songs, &memberships, &seen, summary)
+        })
+        .await;
+    if summary.lock().unwrap().failed.len() > failed_before {
+        return Ok(())
+    }
+
+    let synced_at = chrono::Utc::now().to_rfc3339();
+    let mut tx = conn.begin().await?;
+    let removed = mark_removed(&mut tx, source_id, &memberships, &seen.into_inner().unwrap(), &synced_at).await?;
+    sqlx::query!("UPDATE spt_playlists SET name = $1, synced_at = $2 WHERE id = $3", name, synced_at, source_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    summary.lock().unwrap().removed += removed;
+    Ok(())
+}
+
+/// Wraps a library track like a playlist item so it goes through [`sync_item`].
+fn library_item(track: FullTrack, added_at: Option<chrono::DateTime<chrono::Utc>>) -> PlaylistItem {
+    PlaylistItem {
+        added_at,
+        added_by: None,
+        is_local: track.is_local,
+        track: Some(PlayableItem::Track(track)),
+    }
+}
+
+async fn fetch_liked_songs(spotify: &AuthCodePkceSpotify, options: &SyncOptions) -> anyhow::Result<Vec<PlaylistItem>> {
+    let mut items = Vec::new();
+    let mut offset = 0;
+    loop {
+        let page = options
+            .retry
+            .run(|| spotify.current_user_saved_tracks_manual(None, Some(LIBRARY_PAGE_SIZE), Some(offset)))
+            .await?;
+        offset += page.items.len() as u32;
+        items.extend(page.items.into_iter().map(|saved| library_item(saved.track, Some(saved.added_at))));
+        if page.next.is_none() {
+            return Ok(items)
+        }
+    }
+}
+
+/// Every track of the saved albums, each added when its album was saved. Album
+/// tracks come without their album, so they are fetched again in full.
+async fn fetch_saved_albums(spotify: &AuthCodePkceSpotify, options: &SyncOptions) -> anyhow::Result<Vec<PlaylistItem>> {
+    let mut tracks: Vec<(TrackId<'static>, chrono::DateTime<chrono::Utc>)> = Vec::new();
+    let mut offset = 0;
+    loop {
+        let page = options
+            .retry
+            .run(|| spotify.current_user_saved_albums_manual(None, Some(LIBRARY_PAGE_SIZE), Some(offset)))
+            .await?;
+        offset += page.items.len() as u32;
+        for saved in page.items {
+            let mut album_tracks = saved.album.tracks;
+            loop {
+                tracks.extend(album_tracks.items.into_iter().filter_map(|track| track.id).map(|id| (id, saved.added_at)));
+                if album_tracks.next.is_none() {
+                    break
+                }
+                let album_id: AlbumId = saved.album.id.as_ref();
+                let track_offset = album_tracks.offset + album_tracks.limit;
+                album_tracks = options
+                    .retry
+                    .run(|| spotify.album_track_manual(album_id.as_ref(), Some(LIBRARY_PAGE_SIZE), Some(track_offset)))
+                    .await?;
+            }
+        }
+        if page.next.is_none() {
+            break
+        }
+    }
+
+    let mut items = Vec::with_capacity(tracks.len());
+    for chunk in tracks.chunks(LIBRARY_PAGE_SIZE as usize) {
+        let full_tracks = options
+            .retry
+            .run(|| spotify.tracks(chunk.iter().map(|(id, _)| id.as_ref()), None))
+            .await?;
+        items.extend(full_tracks.into_iter().zip(chunk).map(|(track, (_, added_at))| library_item(track, Some(*added_at))));
+    }
+    Ok(items)
+}
+
+/// The top tracks of every followed artist, in the market of the user.
+async fn fetch_followed_artists(spotify: &AuthCodePkceSpotify, options: &SyncOptions) -> anyhow::Result<Vec<PlaylistItem>> {
+    let mut items = Vec::new();
+    let mut after: Option<String> = None;
+    loop {
+        let page = options
+            .retry
+            .run(|| spotify.current_user_followed_artists(after.as_deref(), Some(LIBRARY_PAGE_SIZE)))
+            .await?;
+        for artist in &page.items {
+            let top_tracks = options
+                .retry
+                .run(|| spotify.artist_top_tracks(artist.id.as_ref(), Market::FromToken))
+                .await?;
+            items.extend(top_tracks.into_iter().map(|track| library_item(track, None)));
+        }
+        after = page.cursors.and_then(|cursors| cursors.after);
+        if page.next.is_none() || after.is_none() {
+            return Ok(items)
+        }
+    }
+}
+
+/// Whether songs of the playlist were synced before some of their details were
+/// stored, the playlist is then walked again even if its snapshot is the same.
+async fn has_incomplete_songs(conn: &sqlx::SqlitePool, playlist_id: &str) -> sqlx::Result<bool> {
+    sqlx::query_scalar!(
+        "SELECT EXISTS (
+            SELECT 1 FROM spt_songs
+            INNER JOIN spt_songs_spt_playlists ON spt_songs_spt_playlists.spt_song_id = spt_songs.id
+            WHERE spt_playlist_id = $1 AND spt_songs_spt_playlists.removed_at IS NULL
+                AND (track_number IS NULL OR explicit IS NULL)
+        ) AS \"incomplete!: bool\"",
+        playlist_id
+    )
+    .fetch_one(conn)
+    .await
+}
+
+/// The synced songs, mapped to whether they miss details added to the schema
+/// since they were written.
+async fn load_known_songs(conn: &sqlx::SqlitePool) -> sqlx::Result<HashMap<String, bool>> {
+    Ok(sqlx::query!("SELECT id, track_number IS NULL OR explicit IS NULL AS \"incomplete!: bool\" FROM spt_songs")
+        .fetch_all(conn)
+        .await?
+        .into_iter()
+        .map(|song| (song.id, song.incomplete))
+        .collect())
+}
+
+async fn load_memberships(conn: &sqlx::SqlitePool, playlist_id: &str) -> sqlx::Result<HashMap<String, Membership>> {
+    Ok(sqlx::query_as!(
+        Membership,
+        "SELECT spt_song_id AS \"spt_song_id!\", position, added_at, added_by, removed_at FROM spt_songs_spt_playlists WHERE spt_playlist_id = $1",
+        playlist_id
+    )
+    .fetch_all(conn)
+    .await?
+    .into_iter()
+    .map(|membership| (membership.spt_song_id.clone(), membership))
+    .collect())
+}
+
+/// Marks the tracks that are no longer in the playlist, returns how many.
+async fn mark_removed(
+    conn: &mut sqlx::SqliteConnection,
+    playlist_id: &str,
+    memberships: &HashMap<String, Membership>,
+    seen: &HashSet<String>,
+    removed_at: &str,
+) -> sqlx::Result<usize> {
+    let removed: Vec<_> = memberships
+        .values()
+        .filter(|membership| membership.removed_at.is_none() && !seen.contains(&membership.spt_song_id))
+        .collect();
+    for membership in &removed {
+        sqlx::query!(
+            "UPDATE spt_songs_spt_playlists SET removed_at = $1 WHERE spt_song_id = $2 AND spt_playlist_id = $3",
+            removed_at,
+            membership.spt_song_id,
+            playlist_id
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(removed.len())
+}
+
+async fn clear_progress(conn: &mut sqlx::SqliteConnection, playlist_id: &str) -> sqlx::Result<()> {
+    sqlx::query!("DELETE FROM spt_sync_progress_songs WHERE spt_playlist_id = $1", playlist_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!("DELETE FROM spt_sync_progress WHERE spt_playlist_id = $1", playlist_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn sync_item(
+    conn: &sqlx::SqlitePool,
+    playlist_id: &str,
+    position: i64,
+    item: PlaylistItem,
+    known_songs: &HashMap<String, bool>,
+    memberships: &HashMap<String, Membership>,
+    seen: &Mutex<HashSet<String>>,
+    summary: &Mutex<SyncSummary>,
+) {
+    // Episodes and local files have nothing to sync
+    let Some(PlayableItem::Track(track)) = item.track else {
+        summary.lock().unwrap().skipped += 1;
+        return
+    };
+    let Some(id) = &track.id else {
+        summary.lock().unwrap().skipped += 1;
+        return
+    };
+    let membership = Membership {
+        spt_song_id: id.id().to_owned(),
+        position: Some(position),
+        added_at: item.added_at.map(|date| date.to_rfc3339()),
+        added_by: item.added_by.map(|user| user.id.id().to_owned()),
+        removed_at: None,
+    };
+    let title = track.name.to_owned();
+
+    let outcome = if !seen.lock().unwrap().insert(membership.spt_song_id.to_owned()) {
+        println!("Duplicate in playlist: {}    {}", membership.spt_song_id, title);
+        Ok(TrackOutcome::Skipped)
+    } else if let Some(&incomplete) = known_songs.get(&membership.spt_song_id) {
+        // Only the playlist membership can have changed, unless the song misses details
+        let changed = memberships.get(&membership.spt_song_id) != Some(&membership);
+        if incomplete || changed {
+            update_known_track(conn, playlist_id, &track, &membership, incomplete, changed)
+                .await
+                .map(|_| TrackOutcome::Updated)
+                .map_err(TrackError::from)
+        } else {
+            Ok(TrackOutcome::Skipped)
+        }
+    } else {
+        insert_track(conn, playlist_id, &track, &membership).await.map(|_| TrackOutcome::Added)
+    };
+
+    let mut summary = summary.lock().unwrap();
+    match outcome {
+        Ok(TrackOutcome::Added) => summary.added += 1,
+        Ok(TrackOutcome::Updated) => summary.updated += 1,
+        Ok(TrackOutcome::Skipped) => summary.skipped += 1,
+        Err(error) => summary.failed.push(FailedTrack { playlist_id: playlist_id.to_owned(), title, error }),
+    }
+}
+
+/// Writes a track with its album, covers, artists and playlist membership,
+/// all or nothing.
+async fn insert_track(
+    conn: &sqlx::SqlitePool,
+    playlist_id: &str,
+    track: &FullTrack,
+    membership: &Membership,
+) -> Result<(), TrackError> {
+    let id = &membership.spt_song_id;
+    let artist = &track.artists.first().ok_or(TrackError::NoArtist)?.name;
+    let album_id = track.album.id.as_ref().ok_or(TrackError::NoAlbumId)?.to_string();
+    let album_type = track.album.album_type.as_ref().ok_or(TrackError::NoAlbumType)?;
+    let duration_ms = track.duration.num_milliseconds();
+    let artists = track
+        .artists
+        .iter()
+        .map(|artist| {
+            let id = artist.id.as_ref().ok_or_else(|| TrackError::NoArtistId(artist.name.to_owned()))?;
+            Ok((id.to_string(), &artist.name))
+        })
+        .collect::<Result<Vec<_>, TrackError>>()?;
+
+    let mut tx = conn.begin().await?;
+    let new_album = sqlx::query!(
+        "INSERT OR IGNORE INTO spt_albums(id, name, kind, release_date, release_date_precision) VALUES ($1, $2, $3, $4, $5)",
+        album_id,
+        track.album.name,
+        album_type,
+        track.album.release_date,
+        track.album.release_date_precision
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected() > 0;
+    if new_album {
+        for image in &track.album.images {
+            sqlx::query!("INSERT OR IGNORE INTO spt_albums_covers(album_id, url, height, width) VALUES ($1, $2, $3, $4)",
+            album_id, image.url, image.height, image.width).execute(&mut *tx).await?;
+        }
+    }
+    let rnd = rand::random::<[u8; 8]>();
+    let rnd = base64::engine::general_purpose::STANDARD_NO_PAD.encode(rnd);
+    sqlx::query!("INSERT INTO songs(id) VALUES ($1)", rnd).execute(&mut *tx).await?;
+    sqlx::query!(
+        "INSERT INTO spt_songs(id, title, artist, album, duration, preview_url, song) VALUES ($1, $2, $3, $4, $5, $6, $7)",
+        id,
+        track.name,
+        artist,
+        album_id,
+        duration_ms,
+        track.preview_url,
+        rnd
+    ).execute(&mut *tx).await?;
+    update_track_details(&mut tx, id, track).await?;
+    for (artist_id, name) in artists {
+        sqlx::query!("INSERT OR IGNORE INTO spt_artists(id, name) VALUES ($1, $2)", artist_id, name)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("INSERT OR IGNORE INTO spt_songs_spt_artists(spt_song_id, spt_artist_id) VALUES ($1, $2)", id, artist_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    upsert_membership(&mut *tx, playlist_id, membership).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Writes the membership when it changed and the track details when the song
+/// was synced before they were stored.
+async fn update_known_track(
+    conn: &sqlx::SqlitePool,
+    playlist_id: &str,
+    track: &FullTrack,
+    membership: &Membership,
+    incomplete: bool,
+    changed: bool,
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+    if incomplete {
+        update_track_details(&mut tx, &membership.spt_song_id, track).await?;
+    }
+    if changed {
+        upsert_membership(&mut *tx, playlist_id, membership).await?;
+    }
+    tx.commit().await
+}
+
+/// The `spt_songs` columns that are backfilled for songs synced before they
+/// existed.
+async fn update_track_details(conn: &mut sqlx::SqliteConnection, id: &str, track: &FullTrack) -> sqlx::Result<()> {
+    let isrc = track.external_ids.get("isrc");
+    let spotify_url = track.external_urls.get("spotify");
+    // Comma separated country codes, empty when the track was requested for a given market
+    let available_markets = track.available_markets.join(",");
+    sqlx::query!(
+        "UPDATE spt_songs SET disc_number = $1, track_number = $2, isrc = $3, explicit = $4, popularity = $5,
+            is_local = $6, available_markets = $7, spotify_url = $8
+        WHERE id = $9",
+        track.disc_number,
+        track.track_number,
+        isrc,
+        track.explicit,
+        track.popularity,
+        track.is_local,
+        available_markets,
+        spotify_url,
+        id
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Membership {
+    spt_song_id: String,
+    position: Option<i64>,
+    added_at: Option<String>,
+    added_by: Option<String>,
+    removed_at: Option<String>,
+}
+
+async fn upsert_membership(
+    conn: impl sqlx::SqliteExecutor<'_>,
+    playlist_id: &str,
+    membership: &Membership,
+) -> sqlx::Result<()> {
+    sqlx::query!(
+        "INSERT INTO spt_songs_spt_playlists(spt_song_id, spt_playlist_id, position, added_at, added_by) VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT(spt_song_id, spt_playlist_id) DO UPDATE SET position = excluded.position, added_at = excluded.added_at, added_by = excluded.added_by, removed_at = NULL",
+        membership.spt_song_id,
+        playlist_id,
+        membership.position,
+        membership.added_at,
+        membership.added_by
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
@@ -1,18 +1,131 @@
+use image::DynamicImage;
+use ratatu_image::{backend::ResizeBackend, picker::Picker, ImageSource, ResizeImage};
 use ratatui::{
     prelude::{Alignment, Buffer, Constraint, Direction, Layout, Rect},
-    widgets::{Paragraph, Widget},
+    style::{Color, Modifier, Style},
+    widgets::{Paragraph, StatefulWidget, Widget, Wrap},
 };
 
-use crate::{DisplayTimestamp, SpotifyUi};
+use crate::review::{DisplayTimestamp, SpotifyUi};
 
-#[derive(Debug, Default, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct Clear(pub SpotifyUi);
 
-impl Widget for Clear {
-    fn render(self, area: Rect, buf: &mut Buffer) {
+/// The decoded cover of the song on screen and what the terminal shows of it,
+/// resized when the layout changes.
+pub struct Cover {
+    source: ImageSource,
+    state: Box<dyn ResizeBackend>,
+}
+
+impl Cover {
+    pub fn new(picker: &mut Picker, image: DynamicImage) -> Self {
+        Cover {
+            source: ImageSource::new(image, picker.font_size()),
+            state: picker.new_state(),
+        }
+    }
+}
+
+/// The cover on top of the details drawn in `area`, and the details.
+fn split(area: Rect) -> (Rect, Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
+        .split(area);
+    (chunks[0], chunks[1])
+}
+
+/// Where the cover is drawn in `area`.
+pub fn cover_area(area: Rect) -> Rect {
+    split(area).0
+}
+
+/// The album initials on a color picked from its name, drawn when there is no
+/// cover to show.
+fn render_placeholder(album_name: &str, area: Rect, buf: &mut Buffer) {
+    // Cells are about twice as high as wide
+    let width = area.width.min(area.height.saturating_mul(2));
+    let area = Rect {
+        x: area.x + (area.width - width) / 2,
+        width,
+        ..area
+    };
+    let top = "\n".repeat(usize::from(area.height.saturating_sub(1) / 2));
+    Paragraph::new(format!("{top}{}", initials(album_name)))
+        .alignment(Alignment::Center)
+        .style(
+            Style::default()
+                .fg(Color::White)
+                .bg(placeholder_color(album_name))
+                .add_modifier(Modifier::BOLD),
+        )
+        .render(area, buf);
+}
+
+/// First letter of the first two words, like `GW` for Global Warming.
+fn initials(name: &str) -> String {
+    let initials: String = name
+        .split_whitespace()
+        .filter_map(|word| word.chars().find(|c| c.is_alphanumeric()))
+        .take(2)
+        .flat_map(char::to_uppercase)
+        .collect();
+    if initials.is_empty() {
+        "?".to_owned()
+    } else {
+        initials
+    }
+}
+
+/// Always the same color for an album, dark enough for white text.
+fn placeholder_color(name: &str) -> Color {
+    let hash = name
+        .bytes()
+        .fold(0xcbf29ce484222325_u64, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3));
+    // HSL with the hue from the hash, 50% saturation and 35% lightness
+    let hue = (hash % 360) as f32 / 60.0;
+    let chroma = 0.35_f32;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u8 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let channel = |value: f32| ((value + 0.175) * 255.0) as u8;
+    Color::Rgb(channel(r), channel(g), channel(b))
+}
+
+impl StatefulWidget for Clear {
+    type State = Option<Cover>;
+
+    fn render(self, area: Rect, buf: &mut Buffer, cover: &mut Option<Cover>) {
         if area.area() == 0 {
             return;
         }
+        let (cover_area, area) = split(area);
+        if let Some(cover) = cover {
+            // Centered, the image keeps its aspect ratio when it shrinks to fit
+            let desired = cover.source.desired;
+            let width = if desired.height > cover_area.height {
+                (u32::from(desired.width) * u32::from(cover_area.height) / u32::from(desired.height)) as u16
+            } else {
+                desired.width
+            };
+            let width = width.min(cover_area.width);
+            let cover_area = Rect {
+                x: cover_area.x + (cover_area.width - width) / 2,
+                width,
+                ..cover_area
+            };
+            ResizeImage::new(&cover.source, None).render(cover_area, buf, &mut cover.state);
+        } else {
+            render_placeholder(&self.0.album_name, cover_area, buf);
+        }
+
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints(
@@ -38,13 +151,10 @@ impl Widget for Clear {
             )
             .split(chunks[3]);
 
-        let pretty_duration = format!(
-            "{}",
-            chrono::Duration::from_std(self.0.duration)
-                .unwrap()
-                .display_timestamp()
-                .unwrap()
-        );
+        let pretty_duration = chrono::Duration::from_std(self.0.duration)
+            .unwrap()
+            .display_timestamp()
+            .unwrap();
         let title = Paragraph::new(format!(
             "Titre: {}\nDurée: {}",
             self.0.title, pretty_duration
@@ -52,34 +162,73 @@ impl Widget for Clear {
         .alignment(Alignment::Center);
         title.render(chunks[0], buf);
 
+        if let Some(message) = &self.0.message {
+            let bottom = Rect {
+                y: chunks[0].bottom().saturating_sub(1),
+                height: chunks[0].height.min(1),
+                ..chunks[0]
+            };
+            Paragraph::new(message.as_str())
+                .style(Style::default().fg(Color::Red))
+                .alignment(Alignment::Center)
+                .render(bottom, buf);
+        }
+
+        let album_details = match self.0.release_year {
+            Some(year) => format!("{}, {year}", self.0.album_kind),
+            None => self.0.album_kind.to_owned(),
+        };
+        let rating = match self.0.rating {
+            Some(rating) => {
+                let rating = rating.clamp(0, 5) as usize;
+                format!("\nNote: {}{}", "★".repeat(rating), "☆".repeat(5 - rating))
+            }
+            None => String::new(),
+        };
+        let tags = if self.0.tags.is_empty() {
+            String::new()
+        } else {
+            format!("\nTags: {}", self.0.tags.join(", "))
+        };
+        let notes = match &self.0.notes {
+            Some(notes) => format!("\nNotes: {notes}"),
+            None => String::new(),
+        };
         let title = Paragraph::new(format!(
-            "Artiste: {}\nAlbum: {} ({})",
-            self.0.artist, self.0.album_name, self.0.album_kind
+            "Artiste: {}\nAlbum: {} ({}){rating}{tags}{notes}",
+            self.0.artist, self.0.album_name, album_details
         ))
+        .wrap(Wrap { trim: true })
         .alignment(Alignment::Center);
         title.render(chunks[2], buf);
 
-        let title = Paragraph::new("P pour preview").alignment(Alignment::Center);
+        if let Some(features) = &self.0.audio_features {
+            let key = match (features.key_name(), features.camelot()) {
+                (Some(name), Some(camelot)) => format!("{name} ({camelot})"),
+                _ => "inconnue".to_owned(),
+            };
+            let title = Paragraph::new(format!(
+                "Tempo: {:.0} BPM  Tonalité: {key}  Mesure: {}/4  Volume: {:.1} dB\nÉnergie: {:.2}  Dansabilité: {:.2}  Valence: {:.2}",
+                features.tempo,
+                features.time_signature,
+                features.loudness,
+                features.energy,
+                features.danceability,
+                features.valence
+            ))
+            .alignment(Alignment::Center);
+            title.render(chunks[1], buf);
+        }
+
+        let title = Paragraph::new("P pour preview, ←/→ ±5 s\nA annoter, T tags").alignment(Alignment::Center);
         title.render(chunks2[0], buf);
 
         let title =
-            Paragraph::new("Y pour ouvrir dans Youtube Search").alignment(Alignment::Center);
+            Paragraph::new("Y pour ouvrir dans Youtube Search\n↑/↓ PgUp/PgDn G/g pour naviguer\n/ rechercher, n/N résultat suivant/précédent").alignment(Alignment::Center);
         title.render(chunks2[1], buf);
 
-        let title = Paragraph::new("Entrée pour aller sur la musique suivante")
+        let title = Paragraph::new("Entrée/K garder, R rejeter, L plus tard\n1-5 noter, U annuler")
             .alignment(Alignment::Center);
         title.render(chunks2[2], buf);
-
-        let img = image::load_from_memory(&self.0.cover_img)
-            .expect("Data from stdin could not be decoded.");
-        let width = (area.width as f32 * 0.20_f32) as u32;
-        let conf = viuer::Config {
-            width: Some(width),
-            x: area.x + (area.width as f32 * 0.5 - (width / 2) as f32) as u16,
-            y: area.y as i16 + (area.height as f32 * 0.07) as i16,
-            use_kitty: false,
-            ..Default::default()
-        };
-        viuer::print(&img, &conf).expect("Image printing failed.");
     }
 }
@@ -0,0 +1,191 @@
+#![allow(dead_code)]
+
+use std::{path::Path, time::Duration};
+
+use exospot::{
+    retry::RetryPolicy,
+    sync::{sync_from_spotify, SyncOptions},
+};
+use rspotify::{AuthCodePkceSpotify, ClientCredsSpotify, Config, Credentials, Token};
+use serde_json::Value;
+use sqlx::SqlitePool;
+use tempfile::TempDir;
+use wiremock::{
+    matchers::{method, path, query_param},
+    Mock, MockServer, ResponseTemplate,
+};
+
+pub const PLAYLIST_ID: &str = "2qv1rmsLVKtnk3n9oLj3vb";
+/// Snapshot of `playlist_tracks.json`, then of `playlist_tracks_updated.json`
+pub const SNAPSHOT: &str = "MSxkN2ZhZTQ2YmQ3YTk0ZWQ2ZmI5NjM5ZjM1MTA3ZDY1YzA5YzA1ZDY3";
+pub const UPDATED_SNAPSHOT: &str = "MixhNDc5YzNhN2IxM2E0NjE2ZjE0YjA0YTU0ZDAyNGQ3OGRkMTQzNTk2";
+
+pub const FEEL_THIS_MOMENT: &str = "4yOn1TEcfsKHUJCL2h1r8I";
+pub const DONT_STOP_THE_PARTY: &str = "0Ph6L4l8dYUuXFmb71Ajnd";
+pub const LEVITATING: &str = "39LLxExYz6ewLAcYrzQQyP";
+/// Only in `playlist_tracks_updated.json`
+pub const TITI_ME_PREGUNTO: &str = "1IHWl5LamUGEuP4ozKQSXZ";
+
+/// Recorded Web API response from `tests/fixtures`.
+pub fn fixture(name: &str) -> Value {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name);
+    let content = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("could not read {}: {e}", path.display()));
+    serde_json::from_str(&content).unwrap()
+}
+
+/// Fresh migrated database, deleted with the returned directory.
+pub async fn database() -> (TempDir, SqlitePool) {
+    let dir = tempfile::tempdir().unwrap();
+    let conn = exospot::db::connect(&dir.path().join("songs.db")).await.unwrap();
+    exospot::db::migrate(&conn).await.unwrap();
+    (dir, conn)
+}
+
+/// Sync options without the extra stages, retrying quickly.
+pub fn options() -> SyncOptions {
+    SyncOptions {
+        full: false,
+        prune: false,
+        concurrency: 4,
+        audio_features: false,
+        covers: false,
+        retry: RetryPolicy {
+            max_retries: 2,
+            base_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(50),
+        },
+    }
+}
+
+/// Database with the songs of the playlist fixture.
+pub async fn synced() -> (TempDir, SqlitePool) {
+    let (dir, conn) = database().await;
+    exospot::playlists::add(&conn, PLAYLIST_ID).await.unwrap();
+    let spotify = MockSpotify::start().await;
+    let tracks = fixture("playlist_tracks.json");
+    playlist_mock(SNAPSHOT, &tracks).mount(&spotify.server).await;
+    tracks_mock(0, &tracks).mount(&spotify.server).await;
+    sync_from_spotify(&conn, &spotify.client().await, None, options()).await.unwrap();
+    (dir, conn)
+}
+
+/// Local stand-in for the accounts service and the Web API.
+pub struct MockSpotify {
+    pub server: MockServer,
+}
+
+impl MockSpotify {
+    pub async fn start() -> Self {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "access_token": "mock-token",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })))
+            .mount(&server)
+            .await;
+        // Every album and artist of the fixtures, whatever IDs are asked for
+        Mock::given(method("GET"))
+            .and(path("/v1/albums/"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(fixture("albums.json")))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/artists/"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(fixture("artists.json")))
+            .mount(&server)
+            .await;
+        MockSpotify { server }
+    }
+
+    pub fn config(&self) -> Config {
+        Config {
+            api_base_url: format!("{}/v1/", self.server.uri()),
+            auth_base_url: format!("{}/", self.server.uri()),
+            ..Default::default()
+        }
+    }
+
+    pub async fn client(&self) -> ClientCredsSpotify {
+        let spotify = ClientCredsSpotify::with_config(Credentials::new("id", "secret"), self.config());
+        spotify.request_token().await.unwrap();
+        spotify
+    }
+
+    /// A client as if `exospot login` had been run, with a token valid for an hour.
+    pub fn user_client(&self) -> AuthCodePkceSpotify {
+        let token = Token {
+            access_token: "mock-user-token".to_owned(),
+            expires_in: chrono::Duration::hours(1),
+            expires_at: Some(chrono::Utc::now() + chrono::Duration::hours(1)),
+            ..Default::default()
+        };
+        let mut spotify = AuthCodePkceSpotify::from_token(token);
+        spotify.config = self.config();
+        spotify
+    }
+}
+
+/// The tracks of a playlist page as a Liked Songs page, local files left out.
+pub fn saved_tracks_page(page: &Value) -> Value {
+    let items: Vec<Value> = page["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|item| item["is_local"] == false)
+        .map(|item| serde_json::json!({ "added_at": item["added_at"], "track": item["track"] }))
+        .collect();
+    serde_json::json!({
+        "href": "https://api.spotify.com/v1/me/tracks?offset=0&limit=50",
+        "items": items,
+        "limit": 50,
+        "next": null,
+        "offset": 0,
+        "previous": null,
+        "total": items.len(),
+    })
+}
+
+pub fn saved_tracks_mock(page: &Value) -> Mock {
+    Mock::given(method("GET"))
+        .and(path("/v1/me/tracks"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(page))
+}
+
+/// `playlist.json` with the given snapshot ID and `tracks` as its first page.
+pub fn playlist_mock(snapshot_id: &str, tracks: &Value) -> Mock {
+    let mut playlist = fixture("playlist.json");
+    playlist["snapshot_id"] = snapshot_id.into();
+    playlist["tracks"] = tracks.clone();
+    Mock::given(method("GET"))
+        .and(path(format!("/v1/playlists/{PLAYLIST_ID}")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(playlist))
+}
+
+/// Serves `page` for the items request starting at `offset`.
+pub fn tracks_mock(offset: u32, page: &Value) -> Mock {
+    tracks_request(offset).respond_with(ResponseTemplate::new(200).set_body_json(page))
+}
+
+pub fn tracks_request(offset: u32) -> wiremock::MockBuilder {
+    Mock::given(method("GET"))
+        .and(path(format!("/v1/playlists/{PLAYLIST_ID}/tracks")))
+        .and(query_param("offset", offset.to_string()))
+}
+
+/// Splits a page fixture in two pages, the first one holding `len` items.
+pub fn split_page(page: &Value, len: usize) -> (Value, Value) {
+    let items = page["items"].as_array().unwrap();
+    let mut first = page.clone();
+    first["items"] = items[..len].into();
+    first["next"] = format!("https://api.spotify.com/v1/playlists/{PLAYLIST_ID}/tracks?offset={len}&limit=100").into();
+    let mut second = page.clone();
+    second["items"] = items[len..].into();
+    second["offset"] = len.into();
+    (first, second)
+}
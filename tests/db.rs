@@ -0,0 +1,20 @@
+mod common;
+
+use common::database;
+use exospot::db;
+
+#[tokio::test]
+async fn migrations_are_pending_until_migrate_runs() {
+    let dir = tempfile::tempdir().unwrap();
+    let conn = db::connect(&dir.path().join("songs.db")).await.unwrap();
+    let pending = db::pending(&conn).await.unwrap();
+    assert!(!pending.is_empty());
+    // Checking does not create the migrations table
+    assert_eq!(db::pending(&conn).await.unwrap(), pending);
+
+    db::migrate(&conn).await.unwrap();
+    assert!(db::pending(&conn).await.unwrap().is_empty());
+
+    let (_dir, conn) = database().await;
+    assert!(db::pending(&conn).await.unwrap().is_empty());
+}
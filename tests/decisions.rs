@@ -0,0 +1,80 @@
+mod common;
+
+use common::database;
+use exospot::decisions::{get, restore, set_rating, set_status, ReviewStatus};
+use sqlx::SqlitePool;
+
+async fn insert_song(conn: &SqlitePool, id: &str) -> String {
+    sqlx::query("INSERT INTO songs(id) VALUES (?)")
+        .bind(id)
+        .execute(conn)
+        .await
+        .unwrap();
+    id.to_owned()
+}
+
+#[tokio::test]
+async fn the_last_decision_on_a_song_replaces_the_previous_one() {
+    let (_dir, conn) = database().await;
+    let song = insert_song(&conn, "aXNyY1VTUk").await;
+    let other = insert_song(&conn, "aXNyY0dCVU").await;
+    assert_eq!(get(&conn, &song).await.unwrap(), None);
+
+    set_status(&conn, &song, ReviewStatus::Later).await.unwrap();
+    set_status(&conn, &song, ReviewStatus::Kept).await.unwrap();
+
+    let review = get(&conn, &song).await.unwrap().unwrap();
+    assert_eq!(review.status, ReviewStatus::Kept);
+    assert_eq!(review.rating, None);
+    assert_eq!(get(&conn, &other).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn ratings_keep_the_decision() {
+    let (_dir, conn) = database().await;
+    let song = insert_song(&conn, "aXNyY1VTUk").await;
+
+    // Rating an unreviewed song leaves it pending
+    set_rating(&conn, &song, 2).await.unwrap();
+    let rated = get(&conn, &song).await.unwrap().unwrap();
+    assert_eq!((rated.status, rated.rating), (ReviewStatus::Pending, Some(2)));
+
+    set_status(&conn, &song, ReviewStatus::Kept).await.unwrap();
+    set_rating(&conn, &song, 4).await.unwrap();
+    let rated = get(&conn, &song).await.unwrap().unwrap();
+    assert_eq!((rated.status, rated.rating), (ReviewStatus::Kept, Some(4)));
+    // Out of range ratings are refused by the database
+    assert!(set_rating(&conn, &song, 6).await.is_err());
+    assert_eq!(get(&conn, &song).await.unwrap().unwrap().rating, Some(4));
+}
+
+#[tokio::test]
+async fn undo_restores_the_review_before_the_decision() {
+    let (_dir, conn) = database().await;
+    let song = insert_song(&conn, "aXNyY1VTUk").await;
+
+    // A decision on an unreviewed song is undone by removing its review
+    set_status(&conn, &song, ReviewStatus::Rejected).await.unwrap();
+    restore(&conn, &song, None).await.unwrap();
+    assert_eq!(get(&conn, &song).await.unwrap(), None);
+
+    set_status(&conn, &song, ReviewStatus::Later).await.unwrap();
+    let before = get(&conn, &song).await.unwrap();
+    set_rating(&conn, &song, 5).await.unwrap();
+    set_status(&conn, &song, ReviewStatus::Kept).await.unwrap();
+    restore(&conn, &song, before.as_ref()).await.unwrap();
+    assert_eq!(get(&conn, &song).await.unwrap(), before);
+}
+
+#[test]
+fn statuses_are_stored_by_name_and_queued_pending_first() {
+    let statuses = [ReviewStatus::Pending, ReviewStatus::Later, ReviewStatus::Kept, ReviewStatus::Rejected];
+    for status in statuses {
+        assert_eq!(status.as_str().parse::<ReviewStatus>().unwrap(), status);
+    }
+    assert!("maybe".parse::<ReviewStatus>().is_err());
+    let mut queue = statuses;
+    queue.reverse();
+    queue.sort_by_key(|status| status.queue_rank());
+    assert_eq!(&queue[..2], [ReviewStatus::Pending, ReviewStatus::Later]);
+}
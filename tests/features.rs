@@ -0,0 +1,70 @@
+use exospot::features::{AudioFeatures, Camelot, FeatureFilter, TempoRange};
+
+fn features(key: i64, mode: i64) -> AudioFeatures {
+    AudioFeatures {
+        tempo: 124.0,
+        key,
+        mode,
+        time_signature: 4,
+        energy: 0.8,
+        valence: 0.5,
+        danceability: 0.7,
+        loudness: -5.0,
+    }
+}
+
+fn camelot(key: i64, mode: i64) -> Option<String> {
+    features(key, mode).camelot().map(|camelot| camelot.to_string())
+}
+
+#[test]
+fn keys_are_placed_on_the_camelot_wheel() {
+    assert_eq!(camelot(0, 1).as_deref(), Some("8B"));
+    assert_eq!(camelot(9, 0).as_deref(), Some("8A"));
+    assert_eq!(camelot(1, 1).as_deref(), Some("3B"));
+    assert_eq!(camelot(4, 0).as_deref(), Some("9A"));
+    assert_eq!(camelot(6, 0).as_deref(), Some("11A"));
+    assert_eq!(camelot(11, 1).as_deref(), Some("1B"));
+    // No key detected
+    assert_eq!(camelot(-1, 1), None);
+    assert_eq!(features(9, 0).key_name().as_deref(), Some("A minor"));
+}
+
+#[test]
+fn camelot_keys_are_parsed() {
+    assert_eq!("8A".parse::<Camelot>().unwrap(), Camelot { number: 8, major: false });
+    assert_eq!(" 12b".parse::<Camelot>().unwrap(), Camelot { number: 12, major: true });
+    for invalid in ["13A", "0B", "A", "8", "8C", ""] {
+        assert!(invalid.parse::<Camelot>().is_err(), "{invalid} was accepted");
+    }
+}
+
+#[test]
+fn tempo_ranges_are_parsed() {
+    assert_eq!("120-128".parse::<TempoRange>().unwrap(), TempoRange { min: 120.0, max: 128.0 });
+    assert_eq!("124 - 124".parse::<TempoRange>().unwrap(), TempoRange { min: 124.0, max: 124.0 });
+    for invalid in ["120-", "-128", "120", "fast-slow", "128-120"] {
+        assert!(invalid.parse::<TempoRange>().is_err(), "{invalid} was accepted");
+    }
+}
+
+#[test]
+fn filters_match_on_every_feature() {
+    let song = features(9, 0);
+    assert!(FeatureFilter::default().matches(Some(&song)));
+    assert!(FeatureFilter::default().matches(None));
+
+    let filter = FeatureFilter {
+        tempo: Some("120-128".parse().unwrap()),
+        key: Some("8A".parse().unwrap()),
+        min_energy: Some(0.8),
+        ..Default::default()
+    };
+    assert!(filter.matches(Some(&song)));
+    assert!(!filter.matches(Some(&AudioFeatures { tempo: 130.0, ..song })));
+    assert!(!filter.matches(Some(&features(0, 1))));
+    assert!(!filter.matches(Some(&AudioFeatures { energy: 0.5, ..song })));
+    // Songs without features never match an active filter
+    assert!(!filter.matches(None));
+    assert!(!FeatureFilter { min_valence: Some(0.0), ..Default::default() }.matches(None));
+}
@@ -0,0 +1,126 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use exospot::http_source::HttpSource;
+use symphonia::core::io::MediaSource;
+use wiremock::{matchers::method, Mock, MockServer, Request, Respond, ResponseTemplate};
+
+/// Serves `body` like a file server, honoring `Range: bytes=N-` when `ranges`.
+struct File {
+    body: Vec<u8>,
+    ranges: bool,
+}
+
+impl Respond for File {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let range = request.headers.get(&"range".into()).filter(|_| self.ranges);
+        let Some(range) = range else {
+            let response = ResponseTemplate::new(200).set_body_bytes(self.body.clone());
+            return match self.ranges {
+                true => response.insert_header("accept-ranges", "bytes"),
+                false => response,
+            };
+        };
+        let start: usize = range
+            .as_str()
+            .strip_prefix("bytes=")
+            .and_then(|range| range.strip_suffix('-'))
+            .and_then(|start| start.parse().ok())
+            .unwrap();
+        ResponseTemplate::new(206)
+            .insert_header("accept-ranges", "bytes")
+            .insert_header(
+                "content-range",
+                format!("bytes {start}-{}/{}", self.body.len() - 1, self.body.len()).as_str(),
+            )
+            .set_body_bytes(self.body[start..].to_vec())
+    }
+}
+
+fn body() -> Vec<u8> {
+    (0..100_000u32).map(|i| (i % 251) as u8).collect()
+}
+
+async fn serve(ranges: bool) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(File { body: body(), ranges })
+        .mount(&server)
+        .await;
+    server
+}
+
+async fn range_requests(server: &MockServer) -> Vec<String> {
+    let requests = server.received_requests().await.unwrap();
+    requests
+        .iter()
+        .filter_map(|request| request.headers.get(&"range".into()))
+        .map(|range| range.as_str().to_owned())
+        .collect()
+}
+
+#[tokio::test]
+async fn seeks_past_the_download_use_range_requests() {
+    let server = serve(true).await;
+    let url = format!("{}/preview.mp3", server.uri());
+
+    let source = tokio::task::spawn_blocking(move || {
+        let mut source = HttpSource::new(reqwest::blocking::Client::new(), &url).unwrap();
+        assert!(source.is_seekable());
+        assert_eq!(source.byte_len(), Some(100_000));
+
+        let mut start = [0; 1000];
+        source.read_exact(&mut start).unwrap();
+        assert_eq!(start[..], body()[..1000]);
+
+        // Back into the downloaded bytes
+        assert_eq!(source.seek(SeekFrom::Start(10)).unwrap(), 10);
+        source.read_exact(&mut start[..10]).unwrap();
+        assert_eq!(start[..10], body()[10..20]);
+
+        // Past them, from the end like a reader of ID3v1 tags
+        assert_eq!(source.seek(SeekFrom::End(-128)).unwrap(), 99_872);
+        let mut tail = Vec::new();
+        source.read_to_end(&mut tail).unwrap();
+        assert_eq!(tail, body()[99_872..]);
+
+        assert_eq!(source.seek(SeekFrom::Current(-50_000)).unwrap(), 50_000);
+        let mut rest = Vec::new();
+        source.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, body()[50_000..]);
+        source
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(range_requests(&server).await, ["bytes=99872-", "bytes=50000-"]);
+    tokio::task::spawn_blocking(move || drop(source)).await.unwrap();
+}
+
+#[tokio::test]
+async fn servers_without_ranges_are_read_forward() {
+    let server = serve(false).await;
+    let url = format!("{}/preview.mp3", server.uri());
+
+    let source = tokio::task::spawn_blocking(move || {
+        let mut source = HttpSource::new(reqwest::blocking::Client::new(), &url).unwrap();
+        assert!(!source.is_seekable());
+        assert_eq!(source.byte_len(), Some(100_000));
+
+        assert_eq!(source.seek(SeekFrom::Start(60_000)).unwrap(), 60_000);
+        let mut byte = [0];
+        source.read_exact(&mut byte).unwrap();
+        assert_eq!(byte[0], body()[60_000]);
+
+        // Everything before is downloaded already
+        source.rewind().unwrap();
+        let mut all = Vec::new();
+        source.read_to_end(&mut all).unwrap();
+        assert_eq!(all, body());
+        source
+    })
+    .await
+    .unwrap();
+
+    assert!(range_requests(&server).await.is_empty());
+    tokio::task::spawn_blocking(move || drop(source)).await.unwrap();
+}
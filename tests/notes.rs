@@ -0,0 +1,81 @@
+mod common;
+
+use common::{synced, FEEL_THIS_MOMENT, LEVITATING};
+use exospot::{
+    export::{export, Format},
+    notes::{get, parse_tags, set_notes, set_tags},
+    search::search,
+};
+use serde_json::Value;
+use sqlx::SqlitePool;
+
+async fn song(conn: &SqlitePool, spt_song_id: &str) -> String {
+    sqlx::query_scalar("SELECT song FROM spt_songs WHERE id = ?")
+        .bind(spt_song_id)
+        .fetch_one(conn)
+        .await
+        .unwrap()
+}
+
+#[test]
+fn tags_are_trimmed_lowercased_and_deduplicated() {
+    assert_eq!(parse_tags("For set B, needs edit,,needs edit , "), ["for set b", "needs edit"]);
+    assert!(parse_tags(" , ").is_empty());
+}
+
+#[tokio::test]
+async fn notes_and_tags_are_saved_per_song() {
+    let (_dir, conn) = synced().await;
+    let moment = song(&conn, FEEL_THIS_MOMENT).await;
+    let levitating = song(&conn, LEVITATING).await;
+
+    set_notes(&conn, &moment, " intro too long ").await.unwrap();
+    set_tags(&conn, &moment, &parse_tags("for set B, needs edit")).await.unwrap();
+    set_tags(&conn, &levitating, &parse_tags("needs edit")).await.unwrap();
+    let annotations = get(&conn, &moment).await.unwrap();
+    assert_eq!(annotations.notes.as_deref(), Some("intro too long"));
+    assert_eq!(annotations.tags, ["for set b", "needs edit"]);
+
+    // Blank notes clear them, tags no song uses anymore are forgotten
+    set_notes(&conn, &moment, "  ").await.unwrap();
+    set_tags(&conn, &moment, &[]).await.unwrap();
+    assert_eq!(get(&conn, &moment).await.unwrap(), Default::default());
+    let tags: Vec<String> = sqlx::query_scalar("SELECT name FROM tags").fetch_all(&conn).await.unwrap();
+    assert_eq!(tags, ["needs edit"]);
+}
+
+#[tokio::test]
+async fn replaced_tags_are_replaced_in_the_search_index() {
+    let (_dir, conn) = synced().await;
+    let levitating = song(&conn, LEVITATING).await;
+
+    set_tags(&conn, &levitating, &parse_tags("for set B")).await.unwrap();
+    assert_eq!(search(&conn, "set b").await.unwrap(), [LEVITATING]);
+    set_tags(&conn, &levitating, &parse_tags("opener")).await.unwrap();
+    assert!(search(&conn, "set b").await.unwrap().is_empty());
+    assert_eq!(search(&conn, "opener").await.unwrap(), [LEVITATING]);
+}
+
+#[tokio::test]
+async fn exports_have_notes_and_tags_columns() {
+    let (dir, conn) = synced().await;
+    let moment = song(&conn, FEEL_THIS_MOMENT).await;
+    set_notes(&conn, &moment, "intro too long").await.unwrap();
+    set_tags(&conn, &moment, &parse_tags("needs edit, for set B")).await.unwrap();
+
+    let path = dir.path().join("songs.json");
+    export(&conn, Format::Json, Some(&path)).await.unwrap();
+    let songs: Vec<Value> = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    let exported = |id: &str| songs.iter().find(|song| song["id"] == id).unwrap().clone();
+    assert_eq!(exported(FEEL_THIS_MOMENT)["notes"], "intro too long");
+    assert_eq!(exported(FEEL_THIS_MOMENT)["tags"], "for set b, needs edit");
+    assert_eq!(exported(LEVITATING)["notes"], Value::Null);
+    assert_eq!(exported(LEVITATING)["tags"], "");
+
+    let path = dir.path().join("songs.csv");
+    export(&conn, Format::Csv, Some(&path)).await.unwrap();
+    let mut reader = csv::Reader::from_path(&path).unwrap();
+    let headers = reader.headers().unwrap().clone();
+    assert!(headers.iter().any(|header| header == "notes"));
+    assert!(headers.iter().any(|header| header == "tags"));
+}
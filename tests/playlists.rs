@@ -0,0 +1,39 @@
+use exospot::playlists::{parse_playlist_id, Source};
+use rspotify::model::{Id, PlaylistId};
+
+const ID: &str = "2qv1rmsLVKtnk3n9oLj3vb";
+
+fn parsed(input: &str) -> String {
+    parse_playlist_id(input).unwrap().id().to_owned()
+}
+
+#[test]
+fn playlist_ids_are_read_from_ids_uris_and_urls() {
+    assert_eq!(parsed(ID), ID);
+    assert_eq!(parsed(&format!(" {ID}\n")), ID);
+    assert_eq!(parsed(&format!("spotify:playlist:{ID}")), ID);
+    assert_eq!(parsed(&format!("https://open.spotify.com/playlist/{ID}")), ID);
+    assert_eq!(parsed(&format!("https://open.spotify.com/intl-fr/playlist/{ID}?si=4f1c2b3a9d8e4f00")), ID);
+    assert_eq!(parsed(&format!("open.spotify.com/playlist/{ID}#top")), ID);
+}
+
+#[test]
+fn other_links_are_not_playlists() {
+    assert!(parse_playlist_id("https://open.spotify.com/track/4yOn1TEcfsKHUJCL2h1r8I").is_err());
+    assert!(parse_playlist_id("https://open.spotify.com/playlist/").is_err());
+    assert!(parse_playlist_id("spotify:track:4yOn1TEcfsKHUJCL2h1r8I").is_err());
+    assert!(parse_playlist_id("not a playlist").is_err());
+}
+
+#[test]
+fn library_sources_have_reserved_ids() {
+    assert_eq!(Source::parse("liked-songs").unwrap(), Source::LikedSongs);
+    assert_eq!(Source::parse(" saved-albums ").unwrap(), Source::SavedAlbums);
+    assert_eq!(Source::parse("followed-artists").unwrap(), Source::FollowedArtists);
+    assert_eq!(Source::parse("liked-songs").unwrap().id(), "liked-songs");
+
+    let playlist = Source::parse(&format!("spotify:playlist:{ID}")).unwrap();
+    assert_eq!(playlist, Source::Playlist(PlaylistId::from_id(ID).unwrap()));
+    assert_eq!(playlist.id(), ID);
+    assert_eq!(playlist.library_name(), None);
+}
@@ -0,0 +1,34 @@
+use crossterm::event::KeyCode;
+use exospot::review::{navigate, next_match};
+
+#[test]
+fn navigation_stays_in_the_list() {
+    assert_eq!(navigate(KeyCode::Down, 3, 30), Some(4));
+    assert_eq!(navigate(KeyCode::Up, 3, 30), Some(2));
+    assert_eq!(navigate(KeyCode::Up, 0, 30), Some(0));
+    assert_eq!(navigate(KeyCode::Down, 29, 30), Some(29));
+    assert_eq!(navigate(KeyCode::Home, 17, 30), Some(0));
+    assert_eq!(navigate(KeyCode::Char('G'), 17, 30), Some(29));
+    assert_eq!(navigate(KeyCode::Char('x'), 17, 30), None);
+    assert_eq!(navigate(KeyCode::Down, 0, 0), None);
+}
+
+#[test]
+fn pages_are_clamped_to_the_ends() {
+    assert_eq!(navigate(KeyCode::PageDown, 3, 30), Some(13));
+    assert_eq!(navigate(KeyCode::PageDown, 25, 30), Some(29));
+    assert_eq!(navigate(KeyCode::PageUp, 13, 30), Some(3));
+    assert_eq!(navigate(KeyCode::PageUp, 4, 30), Some(0));
+}
+
+#[test]
+fn search_matches_wrap_around() {
+    let matches = [2, 5, 9];
+    assert_eq!(next_match(&matches, 2, false), Some(5));
+    assert_eq!(next_match(&matches, 6, false), Some(9));
+    assert_eq!(next_match(&matches, 9, false), Some(2));
+    assert_eq!(next_match(&matches, 5, true), Some(2));
+    assert_eq!(next_match(&matches, 0, true), Some(9));
+    assert_eq!(next_match(&matches, 2, true), Some(9));
+    assert_eq!(next_match(&[], 2, false), None);
+}
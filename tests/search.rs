@@ -0,0 +1,56 @@
+mod common;
+
+use common::{
+    database, fixture, options, playlist_mock, synced, tracks_mock, MockSpotify, DONT_STOP_THE_PARTY, FEEL_THIS_MOMENT,
+    LEVITATING, PLAYLIST_ID, SNAPSHOT, TITI_ME_PREGUNTO, UPDATED_SNAPSHOT,
+};
+use exospot::sync::sync_from_spotify;
+use sqlx::SqlitePool;
+
+async fn search(conn: &SqlitePool, input: &str) -> Vec<String> {
+    let mut ids = exospot::search::search(conn, input).await.unwrap();
+    ids.sort();
+    ids
+}
+
+#[tokio::test]
+async fn sync_indexes_songs_for_search() {
+    let (_dir, conn) = synced().await;
+
+    // Word prefixes of the title, every artist and the album, in any order and without accents
+    assert_eq!(search(&conn, "moment christ").await, [FEEL_THIS_MOMENT]);
+    assert_eq!(search(&conn, "Glöbal pit").await, [DONT_STOP_THE_PARTY, FEEL_THIS_MOMENT]);
+    assert_eq!(search(&conn, "levitating pitbull").await, Vec::<String>::new());
+    assert_eq!(search(&conn, " - ").await, Vec::<String>::new());
+
+    let song: String = sqlx::query_scalar("SELECT song FROM spt_songs WHERE id = ?")
+        .bind(LEVITATING)
+        .fetch_one(&conn)
+        .await
+        .unwrap();
+    exospot::notes::set_tags(&conn, &song, &exospot::notes::parse_tags("for set B")).await.unwrap();
+    assert_eq!(search(&conn, "set b").await, [LEVITATING]);
+}
+
+#[tokio::test]
+async fn partially_failed_sync_still_indexes_the_synced_songs() {
+    let (_dir, conn) = database().await;
+    exospot::playlists::add(&conn, PLAYLIST_ID).await.unwrap();
+    let spotify = MockSpotify::start().await;
+    let client = spotify.client().await;
+    let tracks = fixture("playlist_tracks.json");
+    {
+        let _playlist = playlist_mock(SNAPSHOT, &tracks).mount_as_scoped(&spotify.server).await;
+        let _tracks = tracks_mock(0, &tracks).mount_as_scoped(&spotify.server).await;
+        sync_from_spotify(&conn, &client, None, options()).await.unwrap();
+    }
+
+    // Liked Songs cannot be synced without a login, the playlist still can
+    exospot::playlists::add(&conn, "liked-songs").await.unwrap();
+    let updated = fixture("playlist_tracks_updated.json");
+    playlist_mock(UPDATED_SNAPSHOT, &updated).mount(&spotify.server).await;
+    tracks_mock(0, &updated).mount(&spotify.server).await;
+    assert!(sync_from_spotify(&conn, &client, None, options()).await.is_err());
+
+    assert_eq!(search(&conn, "titi").await, [TITI_ME_PREGUNTO]);
+}
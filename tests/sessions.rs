@@ -0,0 +1,37 @@
+mod common;
+
+use common::database;
+use exospot::sessions::{open, save_position, shuffle_key};
+
+#[tokio::test]
+async fn sessions_resume_with_the_same_seed_until_reshuffled() {
+    let (_dir, conn) = database().await;
+
+    let session = open(&conn, "all", false).await.unwrap();
+    assert_eq!(session.current_spt_song_id, None);
+    save_position(&conn, "all", "3Dv1eDb0MEgF93GpLXlucZ").await.unwrap();
+
+    let resumed = open(&conn, "all", false).await.unwrap();
+    assert_eq!(resumed.seed, session.seed);
+    assert_eq!(resumed.current_spt_song_id.as_deref(), Some("3Dv1eDb0MEgF93GpLXlucZ"));
+    // Sessions are independent
+    assert_ne!(open(&conn, "other", false).await.unwrap().seed, session.seed);
+
+    let reshuffled = open(&conn, "all", true).await.unwrap();
+    assert_ne!(reshuffled.seed, session.seed);
+    assert_eq!(reshuffled.current_spt_song_id, None);
+}
+
+#[test]
+fn shuffle_keys_depend_on_the_seed_only() {
+    let ids = ["3Dv1eDb0MEgF93GpLXlucZ", "0Hf4aIJpsN4Os2f0y0VqWl", "1rfofaqEpACxVEHIZBJe6W", "4EWCNWgDS8707fNSZ1oaA5"];
+    let order = |seed| {
+        let mut ids = ids;
+        ids.sort_by_key(|id| shuffle_key(seed, id));
+        ids
+    };
+    assert_eq!(order(42), order(42));
+    // Stable across Rust versions and platforms
+    assert_eq!(shuffle_key(42, ids[0]), 0xc0d9_f2ba_b91b_ef92);
+    assert!((0..10).any(|seed| order(seed) != order(42)));
+}
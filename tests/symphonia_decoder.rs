@@ -0,0 +1,51 @@
+use std::{io::Cursor, time::Duration};
+
+use exospot::symphonia_decoder::SymphoniaDecoder;
+use rodio::Source;
+use symphonia::core::io::MediaSourceStream;
+
+/// MPEG-1 Layer III, 128 kbit/s, 44.1 kHz, mono, without padding.
+const FRAME_HEADER: [u8; 4] = [0xFF, 0xFB, 0x90, 0xC0];
+const FRAME_LEN: usize = 417;
+const FRAME_DURATION: f64 = 1152.0 / 44100.0;
+const FRAMES: usize = 200;
+
+/// A silent CBR MP3 without a Xing header, every frame has no main data.
+fn silence() -> Vec<u8> {
+    let mut frame = vec![0; FRAME_LEN];
+    frame[..4].copy_from_slice(&FRAME_HEADER);
+    frame.repeat(FRAMES)
+}
+
+fn decoder() -> SymphoniaDecoder {
+    let mss = MediaSourceStream::new(Box::new(Cursor::new(silence())), Default::default());
+    SymphoniaDecoder::new(mss, Some("mp3")).unwrap()
+}
+
+fn assert_close(actual: Duration, expected: Duration) {
+    let difference = actual.as_secs_f64() - expected.as_secs_f64();
+    assert!(difference.abs() < FRAME_DURATION, "{actual:?} is not close to {expected:?}");
+}
+
+#[test]
+fn total_duration_is_known_from_the_frames() {
+    let decoder = decoder();
+    assert_eq!((decoder.channels(), decoder.sample_rate()), (1, 44100));
+    assert_close(decoder.total_duration().unwrap(), Duration::from_secs_f64(FRAMES as f64 * FRAME_DURATION));
+}
+
+#[test]
+fn seeking_moves_the_elapsed_time() {
+    let mut decoder = decoder();
+    assert_eq!(decoder.elapsed(), Duration::ZERO);
+
+    decoder.seek(Duration::from_secs(3)).unwrap();
+    assert_close(decoder.elapsed(), Duration::from_secs(3));
+    // Back before the samples played
+    decoder.seek(Duration::from_millis(1500)).unwrap();
+    assert_close(decoder.elapsed(), Duration::from_millis(1500));
+
+    // Playing goes on from there
+    decoder.by_ref().take(44100).for_each(drop);
+    assert_close(decoder.elapsed(), Duration::from_millis(2500));
+}
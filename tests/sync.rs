@@ -0,0 +1,592 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{
+    database, fixture, options, playlist_mock, saved_tracks_mock, saved_tracks_page, split_page, synced, tracks_mock,
+    tracks_request, MockSpotify, DONT_STOP_THE_PARTY, FEEL_THIS_MOMENT, LEVITATING, PLAYLIST_ID, SNAPSHOT,
+    TITI_ME_PREGUNTO, UPDATED_SNAPSHOT,
+};
+use exospot::sync::{sync_from_spotify, SyncOptions};
+use sqlx::SqlitePool;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+async fn count(conn: &SqlitePool, table: &str) -> i64 {
+    sqlx::query_scalar(&format!("SELECT count(*) FROM {table}"))
+        .fetch_one(conn)
+        .await
+        .unwrap()
+}
+
+async fn setup() -> (tempfile::TempDir, SqlitePool, MockSpotify) {
+    let (dir, conn) = database().await;
+    exospot::playlists::add(&conn, PLAYLIST_ID).await.unwrap();
+    (dir, conn, MockSpotify::start().await)
+}
+
+#[tokio::test]
+async fn sync_writes_songs_albums_covers_and_artists() {
+    let (_dir, conn, spotify) = setup().await;
+    let tracks = fixture("playlist_tracks.json");
+    playlist_mock(SNAPSHOT, &tracks).mount(&spotify.server).await;
+    tracks_mock(0, &tracks).mount(&spotify.server).await;
+
+    sync_from_spotify(&conn, &spotify.client().await, None, options()).await.unwrap();
+
+    // The local file is skipped
+    assert_eq!(count(&conn, "spt_songs").await, 3);
+    assert_eq!(count(&conn, "songs").await, 3);
+    assert_eq!(count(&conn, "spt_albums").await, 2);
+    assert_eq!(count(&conn, "spt_artists").await, 3);
+    assert_eq!(count(&conn, "spt_songs_spt_artists").await, 4);
+
+    let (title, artist, album, duration, preview_url): (String, String, String, i64, Option<String>) =
+        sqlx::query_as("SELECT title, artist, album, duration, preview_url FROM spt_songs WHERE id = ?")
+            .bind(FEEL_THIS_MOMENT)
+            .fetch_one(&conn)
+            .await
+            .unwrap();
+    assert_eq!(title, "Feel This Moment");
+    assert_eq!(artist, "Pitbull");
+    assert_eq!(album, "spotify:album:4kQpaRbCvnOq7QJVtMrbE7");
+    assert_eq!(duration, 229506);
+    assert_eq!(preview_url.as_deref(), Some("https://p.scdn.co/mp3-preview/4yOn1TEcfsKHUJCL2h1r8I"));
+
+    let (name, kind): (String, String) = sqlx::query_as("SELECT name, kind FROM spt_albums WHERE id = ?")
+        .bind(&album)
+        .fetch_one(&conn)
+        .await
+        .unwrap();
+    assert_eq!((name.as_str(), kind.as_str()), ("Global Warming", "album"));
+
+    let artists: Vec<String> = sqlx::query_scalar(
+        "SELECT spt_artists.name FROM spt_songs_spt_artists
+        INNER JOIN spt_artists ON spt_artists.id = spt_songs_spt_artists.spt_artist_id
+        WHERE spt_song_id = ? ORDER BY spt_artists.name",
+    )
+    .bind(FEEL_THIS_MOMENT)
+    .fetch_all(&conn)
+    .await
+    .unwrap();
+    assert_eq!(artists, ["Christina Aguilera", "Pitbull"]);
+
+    let memberships: Vec<(String, i64, String, String)> = sqlx::query_as(
+        "SELECT spt_song_id, position, added_at, added_by FROM spt_songs_spt_playlists
+        WHERE spt_playlist_id = ? ORDER BY position",
+    )
+    .bind(PLAYLIST_ID)
+    .fetch_all(&conn)
+    .await
+    .unwrap();
+    assert_eq!(
+        memberships,
+        [
+            (FEEL_THIS_MOMENT.to_owned(), 0, "2023-08-30T18:02:11+00:00".to_owned(), "galacs".to_owned()),
+            (DONT_STOP_THE_PARTY.to_owned(), 1, "2023-08-30T18:03:40+00:00".to_owned(), "djmix".to_owned()),
+            (LEVITATING.to_owned(), 2, "2023-08-31T09:12:00+00:00".to_owned(), "galacs".to_owned()),
+        ]
+    );
+
+    let (name, snapshot_id): (String, String) = sqlx::query_as("SELECT name, snapshot_id FROM spt_playlists WHERE id = ?")
+        .bind(PLAYLIST_ID)
+        .fetch_one(&conn)
+        .await
+        .unwrap();
+    assert_eq!((name.as_str(), snapshot_id.as_str()), ("Exospot inbox", SNAPSHOT));
+}
+
+#[tokio::test]
+async fn album_details_are_synced() {
+    let (_dir, conn) = synced().await;
+
+    let details: (String, String, String, String, i64) = sqlx::query_as(
+        "SELECT release_date, release_date_precision, label, upc, total_tracks FROM spt_albums WHERE id = ?",
+    )
+    .bind("spotify:album:4kQpaRbCvnOq7QJVtMrbE7")
+    .fetch_one(&conn)
+    .await
+    .unwrap();
+    assert_eq!(
+        details,
+        (
+            "2012-11-16".to_owned(),
+            "day".to_owned(),
+            "Mr.305/Polo Grounds Music/RCA Records".to_owned(),
+            "886443671584".to_owned(),
+            12
+        )
+    );
+}
+
+#[tokio::test]
+async fn track_identifiers_are_stored() {
+    let (_dir, conn) = synced().await;
+
+    let identifiers: (String, bool, i64, bool, String, String) = sqlx::query_as(
+        "SELECT isrc, explicit, popularity, is_local, available_markets, spotify_url FROM spt_songs WHERE id = ?",
+    )
+    .bind(LEVITATING)
+    .fetch_one(&conn)
+    .await
+    .unwrap();
+    assert_eq!(
+        identifiers,
+        (
+            "GBAHT1901121".to_owned(),
+            true,
+            84,
+            false,
+            "FR,US".to_owned(),
+            format!("https://open.spotify.com/track/{LEVITATING}")
+        )
+    );
+}
+
+#[tokio::test]
+async fn artist_genres_images_and_popularity_are_stored() {
+    let (_dir, conn) = synced().await;
+
+    let (popularity, followers): (i64, i64) = sqlx::query_as("SELECT popularity, followers FROM spt_artists WHERE name = 'Pitbull'")
+        .fetch_one(&conn)
+        .await
+        .unwrap();
+    assert_eq!((popularity, followers), (82, 10912004));
+    let genres: Vec<String> = sqlx::query_scalar(
+        "SELECT spt_genre FROM spt_artists_spt_genres
+        INNER JOIN spt_artists ON spt_artists.id = spt_artists_spt_genres.spt_artist_id
+        WHERE spt_artists.name = 'Pitbull' ORDER BY spt_genre",
+    )
+    .fetch_all(&conn)
+    .await
+    .unwrap();
+    assert_eq!(genres, ["dance pop", "miami hip hop", "pop"]);
+    // Shared genres are stored once
+    assert_eq!(count(&conn, "spt_genres").await, 4);
+    assert_eq!(count(&conn, "spt_artists_images").await, 9);
+}
+
+#[tokio::test]
+async fn every_cover_size_is_stored() {
+    let (_dir, conn) = synced().await;
+
+    let covers: Vec<(String, i64)> =
+        sqlx::query_as("SELECT url, height FROM spt_albums_covers WHERE album_id = ? ORDER BY height")
+            .bind("spotify:album:4kQpaRbCvnOq7QJVtMrbE7")
+            .fetch_all(&conn)
+            .await
+            .unwrap();
+    // Every size is kept
+    assert_eq!(covers.iter().map(|(_, height)| *height).collect::<Vec<_>>(), [64, 300, 640]);
+    assert!(covers.iter().all(|(url, _)| url.starts_with("https://i.scdn.co/image/4kQpaRbCvnOq7QJVtMrbE7")));
+}
+
+#[tokio::test]
+async fn unchanged_snapshot_is_not_paged_again() {
+    let (_dir, conn, spotify) = setup().await;
+    let tracks = fixture("playlist_tracks.json");
+    playlist_mock(SNAPSHOT, &tracks).mount(&spotify.server).await;
+    tracks_mock(0, &tracks).expect(1).mount(&spotify.server).await;
+    let client = spotify.client().await;
+
+    sync_from_spotify(&conn, &client, None, options()).await.unwrap();
+    sync_from_spotify(&conn, &client, None, options()).await.unwrap();
+
+    assert_eq!(count(&conn, "spt_songs").await, 3);
+}
+
+#[tokio::test]
+async fn removed_tracks_are_marked_then_pruned() {
+    let (_dir, conn, spotify) = setup().await;
+    let client = spotify.client().await;
+    let tracks = fixture("playlist_tracks.json");
+    {
+        let _playlist = playlist_mock(SNAPSHOT, &tracks).mount_as_scoped(&spotify.server).await;
+        let _tracks = tracks_mock(0, &tracks).mount_as_scoped(&spotify.server).await;
+        sync_from_spotify(&conn, &client, None, options()).await.unwrap();
+    }
+
+    let updated = fixture("playlist_tracks_updated.json");
+    playlist_mock(UPDATED_SNAPSHOT, &updated).mount(&spotify.server).await;
+    tracks_mock(0, &updated).mount(&spotify.server).await;
+    sync_from_spotify(&conn, &client, None, options()).await.unwrap();
+
+    let memberships: Vec<(String, i64, Option<String>)> = sqlx::query_as(
+        "SELECT spt_song_id, position, removed_at FROM spt_songs_spt_playlists
+        WHERE spt_playlist_id = ? ORDER BY spt_song_id",
+    )
+    .bind(PLAYLIST_ID)
+    .fetch_all(&conn)
+    .await
+    .unwrap();
+    let position = |id: &str| memberships.iter().find(|m| m.0 == id).map(|m| (m.1, m.2.is_some()));
+    assert_eq!(position(LEVITATING), Some((0, false)));
+    assert_eq!(position(DONT_STOP_THE_PARTY), Some((1, false)));
+    assert_eq!(position(TITI_ME_PREGUNTO), Some((2, false)));
+    assert_eq!(position(FEEL_THIS_MOMENT), Some((0, true)));
+    assert_eq!(count(&conn, "spt_songs").await, 4);
+
+    // Decisions taken on both a removed and a kept song, to check the prune cascades
+    let songs: Vec<String> = sqlx::query_scalar("SELECT song FROM spt_songs WHERE id IN (?, ?)")
+        .bind(FEEL_THIS_MOMENT)
+        .bind(LEVITATING)
+        .fetch_all(&conn)
+        .await
+        .unwrap();
+    for song in &songs {
+        exospot::decisions::set_status(&conn, song, exospot::decisions::ReviewStatus::Kept).await.unwrap();
+    }
+    // Tags of both songs, "for set b" is only on the removed one
+    for (id, tags) in [(FEEL_THIS_MOMENT, "for set b, needs edit"), (LEVITATING, "needs edit")] {
+        let song: String = sqlx::query_scalar("SELECT song FROM spt_songs WHERE id = ?")
+            .bind(id)
+            .fetch_one(&conn)
+            .await
+            .unwrap();
+        exospot::notes::set_tags(&conn, &song, &exospot::notes::parse_tags(tags)).await.unwrap();
+    }
+
+    sync_from_spotify(&conn, &client, None, SyncOptions { prune: true, ..options() }).await.unwrap();
+
+    assert_eq!(count(&conn, "spt_songs").await, 3);
+    assert_eq!(count(&conn, "songs").await, 3);
+    // Global Warming is still referenced by Don't Stop The Party
+    assert_eq!(count(&conn, "spt_albums").await, 3);
+    // Christina Aguilera was only on Feel This Moment
+    let christina: i64 = sqlx::query_scalar("SELECT count(*) FROM spt_artists WHERE name = 'Christina Aguilera'")
+        .fetch_one(&conn)
+        .await
+        .unwrap();
+    assert_eq!(christina, 0);
+    assert_eq!(count(&conn, "spt_artists").await, 3);
+    assert_eq!(count(&conn, "spt_artists_images").await, 9);
+    // Only the review of the song still in the playlist is left
+    assert_eq!(count(&conn, "song_reviews").await, 1);
+    assert_eq!(count(&conn, "songs_tags").await, 1);
+    assert_eq!(count(&conn, "tags").await, 1);
+    // Bad Bunny brought three genres, Christina Aguilera none of her own
+    assert_eq!(count(&conn, "spt_genres").await, 7);
+}
+
+#[tokio::test]
+async fn covers_are_cached_once() {
+    let (_dir, conn, spotify) = setup().await;
+    let tracks = fixture("playlist_tracks.json");
+    playlist_mock(SNAPSHOT, &tracks).mount(&spotify.server).await;
+    tracks_mock(0, &tracks).mount(&spotify.server).await;
+    sync_from_spotify(&conn, &spotify.client().await, None, options()).await.unwrap();
+
+    // Only the smallest cover of Global Warming can be downloaded
+    sqlx::query("UPDATE spt_albums_covers SET url = ? || '/covers/' || substr(url, length('https://i.scdn.co/image/') + 1)")
+        .bind(spotify.server.uri())
+        .execute(&conn)
+        .await
+        .unwrap();
+    Mock::given(method("GET"))
+        .and(path("/covers/4kQpaRbCvnOq7QJVtMrbE7-64"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(b"jpeg".to_vec()))
+        .expect(1)
+        .mount(&spotify.server)
+        .await;
+
+    exospot::covers::cache_missing(&conn, 4).await.unwrap();
+    assert_eq!(count(&conn, "spt_covers_data").await, 1);
+    let sizes = exospot::covers::sizes(&conn, "spotify:album:4kQpaRbCvnOq7QJVtMrbE7").await.unwrap();
+    let pick = |min_width| exospot::covers::pick(&sizes, min_width).and_then(|cover| cover.width);
+    assert_eq!((pick(0), pick(64), pick(65), pick(300), pick(2000)), (Some(64), Some(64), Some(300), Some(300), Some(640)));
+    let url = format!("{}/covers/4kQpaRbCvnOq7QJVtMrbE7-64", spotify.server.uri());
+    let cached = exospot::covers::get(&conn, &reqwest::Client::new(), &url).await.unwrap();
+    assert_eq!(cached.as_ref(), b"jpeg");
+    // The failed one is tried again, the cached one is not downloaded twice
+    exospot::covers::cache_missing(&conn, 4).await.unwrap();
+    assert_eq!(count(&conn, "spt_covers_data").await, 1);
+}
+
+#[tokio::test]
+async fn removed_songs_are_exported_without_the_playlist() {
+    let (dir, conn, spotify) = setup().await;
+    let client = spotify.client().await;
+    let tracks = fixture("playlist_tracks.json");
+    {
+        let _playlist = playlist_mock(SNAPSHOT, &tracks).mount_as_scoped(&spotify.server).await;
+        let _tracks = tracks_mock(0, &tracks).mount_as_scoped(&spotify.server).await;
+        sync_from_spotify(&conn, &client, None, options()).await.unwrap();
+    }
+    let updated = fixture("playlist_tracks_updated.json");
+    playlist_mock(UPDATED_SNAPSHOT, &updated).mount(&spotify.server).await;
+    tracks_mock(0, &updated).mount(&spotify.server).await;
+    sync_from_spotify(&conn, &client, None, options()).await.unwrap();
+
+    let path = dir.path().join("songs.json");
+    exospot::export::export(&conn, exospot::export::Format::Json, Some(&path)).await.unwrap();
+    let songs: Vec<serde_json::Value> = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    let playlists = |id: &str| songs.iter().find(|song| song["id"] == id).unwrap()["playlists"].clone();
+    assert_eq!(playlists(FEEL_THIS_MOMENT), "");
+    assert_eq!(playlists(LEVITATING), "Exospot inbox");
+}
+
+#[tokio::test]
+async fn failed_track_does_not_stop_the_other_songs_details() {
+    let (_dir, conn, spotify) = setup().await;
+    let mut tracks = fixture("playlist_tracks.json");
+    // Levitating cannot be written without its album type
+    tracks["items"][2]["track"]["album"]["album_type"] = serde_json::Value::Null;
+    playlist_mock(SNAPSHOT, &tracks).mount(&spotify.server).await;
+    tracks_mock(0, &tracks).mount(&spotify.server).await;
+
+    let error = sync_from_spotify(&conn, &spotify.client().await, None, SyncOptions { prune: true, ..options() })
+        .await
+        .unwrap_err();
+    assert_eq!(error.to_string(), "1 tracks failed to sync");
+
+    assert_eq!(count(&conn, "spt_songs").await, 2);
+    let total_tracks: Option<i64> = sqlx::query_scalar("SELECT total_tracks FROM spt_albums WHERE id = ?")
+        .bind("spotify:album:4kQpaRbCvnOq7QJVtMrbE7")
+        .fetch_one(&conn)
+        .await
+        .unwrap();
+    assert_eq!(total_tracks, Some(12));
+    let popularity: Option<i64> = sqlx::query_scalar("SELECT popularity FROM spt_artists WHERE name = 'Pitbull'")
+        .fetch_one(&conn)
+        .await
+        .unwrap();
+    assert_eq!(popularity, Some(82));
+    assert_eq!(count(&conn, "spt_genres").await, 3);
+}
+
+#[tokio::test]
+async fn rate_limited_requests_are_retried() {
+    let (_dir, conn, spotify) = setup().await;
+    let tracks = fixture("playlist_tracks.json");
+    playlist_mock(SNAPSHOT, &tracks).mount(&spotify.server).await;
+    tracks_request(0)
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&spotify.server)
+        .await;
+    tracks_request(0)
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .with_priority(2)
+        .mount(&spotify.server)
+        .await;
+    tracks_mock(0, &tracks).mount(&spotify.server).await;
+
+    sync_from_spotify(&conn, &spotify.client().await, None, options()).await.unwrap();
+
+    assert_eq!(count(&conn, "spt_songs").await, 3);
+}
+
+#[tokio::test]
+async fn long_retry_after_fails_instead_of_waiting() {
+    let (_dir, conn, spotify) = setup().await;
+    let tracks = fixture("playlist_tracks.json");
+    playlist_mock(SNAPSHOT, &tracks).mount(&spotify.server).await;
+    tracks_request(0)
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "3600"))
+        .expect(1)
+        .mount(&spotify.server)
+        .await;
+
+    let client = spotify.client().await;
+    let sync = sync_from_spotify(&conn, &client, None, options());
+    let result = tokio::time::timeout(Duration::from_secs(10), sync).await.expect("the sync waited for Retry-After");
+    assert!(result.is_err());
+    assert_eq!(count(&conn, "spt_songs").await, 0);
+}
+
+#[tokio::test]
+async fn interrupted_sync_resumes_after_the_last_complete_page() {
+    let (_dir, conn, spotify) = setup().await;
+    let client = spotify.client().await;
+    let tracks = fixture("playlist_tracks.json");
+    let (first, second) = split_page(&tracks, 2);
+    playlist_mock(SNAPSHOT, &first).mount(&spotify.server).await;
+    {
+        let _first = tracks_mock(0, &first).expect(1).mount_as_scoped(&spotify.server).await;
+        let _second = tracks_request(2)
+            .respond_with(ResponseTemplate::new(500))
+            .mount_as_scoped(&spotify.server)
+            .await;
+        assert!(sync_from_spotify(&conn, &client, None, options()).await.is_err());
+    }
+    assert_eq!(count(&conn, "spt_songs").await, 2);
+    let snapshot_id: Option<String> = sqlx::query_scalar("SELECT snapshot_id FROM spt_playlists")
+        .fetch_one(&conn)
+        .await
+        .unwrap();
+    assert_eq!(snapshot_id, None);
+
+    // The first page is not requested again
+    tracks_mock(0, &first).expect(0).mount(&spotify.server).await;
+    tracks_mock(2, &second).expect(1).mount(&spotify.server).await;
+    sync_from_spotify(&conn, &client, None, options()).await.unwrap();
+
+    assert_eq!(count(&conn, "spt_songs").await, 3);
+    let removed: i64 = sqlx::query_scalar("SELECT count(*) FROM spt_songs_spt_playlists WHERE removed_at IS NOT NULL")
+        .fetch_one(&conn)
+        .await
+        .unwrap();
+    assert_eq!(removed, 0);
+    assert_eq!(count(&conn, "spt_sync_progress").await, 0);
+    assert_eq!(count(&conn, "spt_sync_progress_songs").await, 0);
+}
+
+#[tokio::test]
+async fn liked_songs_are_synced_with_the_user_login() {
+    let (_dir, conn) = database().await;
+    exospot::playlists::add(&conn, "liked-songs").await.unwrap();
+    let spotify = MockSpotify::start().await;
+    let liked = saved_tracks_page(&fixture("playlist_tracks.json"));
+    saved_tracks_mock(&liked).up_to_n_times(1).mount(&spotify.server).await;
+
+    // App credentials cannot read the user library
+    assert!(sync_from_spotify(&conn, &spotify.client().await, None, options()).await.is_err());
+    assert_eq!(count(&conn, "spt_songs").await, 0);
+
+    let user = spotify.user_client();
+    sync_from_spotify(&conn, &user, Some(&user), options()).await.unwrap();
+    let memberships: Vec<(String, i64, String)> = sqlx::query_as(
+        "SELECT spt_song_id, position, added_at FROM spt_songs_spt_playlists
+        WHERE spt_playlist_id = 'liked-songs' ORDER BY position",
+    )
+    .fetch_all(&conn)
+    .await
+    .unwrap();
+    assert_eq!(
+        memberships,
+        [
+            (FEEL_THIS_MOMENT.to_owned(), 0, "2023-08-30T18:02:11+00:00".to_owned()),
+            (DONT_STOP_THE_PARTY.to_owned(), 1, "2023-08-30T18:03:40+00:00".to_owned()),
+            (LEVITATING.to_owned(), 2, "2023-08-31T09:12:00+00:00".to_owned()),
+        ]
+    );
+
+    // Unliking a song marks it removed on the next sync
+    let (mut kept, _) = split_page(&liked, 2);
+    kept["next"] = serde_json::Value::Null;
+    saved_tracks_mock(&kept).mount(&spotify.server).await;
+    sync_from_spotify(&conn, &user, Some(&user), options()).await.unwrap();
+    let removed: Vec<String> = sqlx::query_scalar("SELECT spt_song_id FROM spt_songs_spt_playlists WHERE removed_at IS NOT NULL")
+        .fetch_all(&conn)
+        .await
+        .unwrap();
+    assert_eq!(removed, [LEVITATING]);
+}
+
+#[tokio::test]
+async fn songs_synced_before_track_details_are_backfilled() {
+    let (_dir, conn, spotify) = setup().await;
+    let tracks = fixture("playlist_tracks.json");
+    tracks_mock(0, &tracks).mount(&spotify.server).await;
+    playlist_mock(SNAPSHOT, &tracks).mount(&spotify.server).await;
+    let client = spotify.client().await;
+    sync_from_spotify(&conn, &client, None, options()).await.unwrap();
+
+    // As left by an older version
+    sqlx::query("UPDATE spt_songs SET disc_number = NULL, track_number = NULL").execute(&conn).await.unwrap();
+    sqlx::query("UPDATE spt_songs SET isrc = NULL, explicit = NULL, popularity = NULL WHERE id = ?")
+        .bind(FEEL_THIS_MOMENT)
+        .execute(&conn)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE spt_albums SET label = NULL, upc = NULL, total_tracks = NULL").execute(&conn).await.unwrap();
+    // The snapshot did not change
+    sync_from_spotify(&conn, &client, None, options()).await.unwrap();
+
+    let numbers: Vec<(String, i64, i64)> =
+        sqlx::query_as("SELECT id, disc_number, track_number FROM spt_songs ORDER BY track_number")
+            .fetch_all(&conn)
+            .await
+            .unwrap();
+    assert_eq!(
+        numbers,
+        [
+            (FEEL_THIS_MOMENT.to_owned(), 1, 3),
+            (LEVITATING.to_owned(), 1, 5),
+            (DONT_STOP_THE_PARTY.to_owned(), 1, 9),
+        ]
+    );
+    let missing: i64 = sqlx::query_scalar("SELECT count(*) FROM spt_albums WHERE total_tracks IS NULL")
+        .fetch_one(&conn)
+        .await
+        .unwrap();
+    assert_eq!(missing, 0);
+    let isrc: Option<String> = sqlx::query_scalar("SELECT isrc FROM spt_songs WHERE id = ?")
+        .bind(FEEL_THIS_MOMENT)
+        .fetch_one(&conn)
+        .await
+        .unwrap();
+    assert_eq!(isrc.as_deref(), Some("USRC11200786"));
+}
+
+#[tokio::test]
+async fn removed_songs_missing_details_do_not_page_the_playlist_again() {
+    let (_dir, conn, spotify) = setup().await;
+    let client = spotify.client().await;
+    let tracks = fixture("playlist_tracks.json");
+    {
+        let _playlist = playlist_mock(SNAPSHOT, &tracks).mount_as_scoped(&spotify.server).await;
+        let _tracks = tracks_mock(0, &tracks).mount_as_scoped(&spotify.server).await;
+        sync_from_spotify(&conn, &client, None, options()).await.unwrap();
+    }
+    let updated = fixture("playlist_tracks_updated.json");
+    playlist_mock(UPDATED_SNAPSHOT, &updated).mount(&spotify.server).await;
+    tracks_mock(0, &updated).expect(1).mount(&spotify.server).await;
+    sync_from_spotify(&conn, &client, None, options()).await.unwrap();
+
+    // As left by an older version, the song is not in the playlist anymore to be backfilled
+    sqlx::query("UPDATE spt_songs SET track_number = NULL WHERE id = ?")
+        .bind(FEEL_THIS_MOMENT)
+        .execute(&conn)
+        .await
+        .unwrap();
+    sync_from_spotify(&conn, &client, None, options()).await.unwrap();
+}
+
+#[tokio::test]
+async fn audio_features_are_imported_when_asked_for() {
+    let (_dir, conn, spotify) = setup().await;
+    let tracks = fixture("playlist_tracks.json");
+    playlist_mock(SNAPSHOT, &tracks).mount(&spotify.server).await;
+    tracks_mock(0, &tracks).mount(&spotify.server).await;
+    let features = fixture("audio_features.json");
+    let features = features["audio_features"].as_array().unwrap();
+    // Spotify has no features for Don't Stop The Party, the batch holds a null
+    Mock::given(method("GET"))
+        .and(path("/v1/audio-features/"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "audio_features": [features[0], null, features[2]],
+        })))
+        .mount(&spotify.server)
+        .await;
+    for track in [&features[0], &features[2]] {
+        Mock::given(method("GET"))
+            .and(path(format!("/v1/audio-features/{}", track["id"].as_str().unwrap())))
+            .respond_with(ResponseTemplate::new(200).set_body_json(track))
+            .mount(&spotify.server)
+            .await;
+    }
+    let client = spotify.client().await;
+
+    sync_from_spotify(&conn, &client, None, options()).await.unwrap();
+    assert_eq!(count(&conn, "spt_audio_features").await, 0);
+
+    sync_from_spotify(&conn, &client, None, SyncOptions { audio_features: true, ..options() }).await.unwrap();
+    // rspotify parses the features as f32
+    let imported: Vec<(String, f32, i64, i64)> =
+        sqlx::query_as("SELECT spt_song_id, tempo, key, mode FROM spt_audio_features ORDER BY tempo")
+            .fetch_all(&conn)
+            .await
+            .unwrap();
+    assert_eq!(
+        imported,
+        [
+            (LEVITATING.to_owned(), 102.977, 6, 0),
+            (FEEL_THIS_MOMENT.to_owned(), 135.956, 7, 1),
+        ]
+    );
+}